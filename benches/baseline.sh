@@ -0,0 +1,38 @@
+#!/bin/sh
+# Keeps the Criterion baseline of the engine benchmarks in the repository, under benches/baseline.
+#
+#   benches/baseline.sh save [criterion options]      measure and store the numbers
+#   benches/baseline.sh compare [criterion options]   measure and compare against the stored numbers
+#
+# Criterion options narrow the run, e.g. `benches/baseline.sh compare update_posterior`.
+set -eu
+cd "$(dirname "$0")/.."
+name=main
+stored=benches/baseline
+criterion=target/criterion
+command=${1:-}
+[ $# -gt 0 ] && shift
+
+case "$command" in
+save)
+    cargo bench --bench engine -- --save-baseline "$name" "$@"
+    # a full run replaces the stored baseline, a narrowed one only updates what it measured
+    [ $# -eq 0 ] && rm -rf "$stored"
+    (cd "$criterion" && find . -type d -name "$name") | while read -r dir; do
+        mkdir -p "$stored/$dir"
+        cp "$criterion/$dir"/*.json "$stored/$dir"
+    done
+    ;;
+compare)
+    (cd "$stored" && find . -type d -name "$name") | while read -r dir; do
+        mkdir -p "$criterion/$dir"
+        cp "$stored/$dir"/*.json "$criterion/$dir"
+    done
+    # benchmarks added since the baseline was saved are measured without a comparison
+    cargo bench --bench engine -- --baseline-lenient "$name" "$@"
+    ;;
+*)
+    echo "usage: $0 save|compare [criterion options]" >&2
+    exit 1
+    ;;
+esac
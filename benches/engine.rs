@@ -0,0 +1,183 @@
+// Criterion benchmarks of the game engine on boards from the normal 9x12 up to 1000x1000, with
+// every sensor model. Distances are always Manhattan distances, the engine has no other metric, so
+// the benchmarks vary what does change: the board, the sensor model, and for the particle filter
+// the number of particles and of readings so far. `benches/baseline.sh` saves and compares against
+// the baseline in `benches/baseline`, see `docs/benchmarks.md`.
+use bust_ghost::game::{Game, GameSettings};
+use bust_ghost::inference::{ExactGrid, InferenceEngine, ParticleFilter, DEFAULT_PARTICLES};
+use bust_ghost::preset::Preset;
+use bust_ghost::solver::Solver;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use std::time::Duration;
+
+// (rows, columns)
+const SIZES: [(i32, i32); 6] = [(9, 12), (50, 50), (100, 100), (250, 250), (500, 500), (1000, 1000)];
+// From the sharpest to the flattest sensor tables and direction hints
+const SENSORS: [Preset; 4] = Preset::ALL;
+
+fn settings(preset: Preset, (rows, columns): (i32, i32)) -> GameSettings {
+    GameSettings {
+        width: columns,
+        height: rows,
+        ..preset.settings()
+    }
+}
+
+fn game(preset: Preset, size: (i32, i32)) -> Game {
+    let mut game = Game::from_seed(settings(preset, size), 42);
+    game.place_ghost();
+    game.compute_initial_prior_probabilities();
+    game
+}
+
+fn variants() -> impl Iterator<Item = (Preset, (i32, i32), String)> {
+    SENSORS.into_iter().flat_map(|preset| {
+        SIZES
+            .into_iter()
+            .map(move |(rows, columns)| (preset, (rows, columns), format!("{}/{}x{}", preset, rows, columns)))
+    })
+}
+
+fn priors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_initial_prior_probabilities");
+    for (rows, columns) in SIZES {
+        let mut game = game(Preset::Normal, (rows, columns));
+        group.bench_function(BenchmarkId::from_parameter(format!("{}x{}", rows, columns)), |b| {
+            b.iter(|| game.compute_initial_prior_probabilities())
+        });
+    }
+    group.finish();
+}
+
+fn distance_sense(c: &mut Criterion) {
+    let mut group = c.benchmark_group("distance_sense");
+    for (preset, size, id) in variants() {
+        let mut game = game(preset, size);
+        let (x, y) = (size.0 / 2, size.1 / 2);
+        group.bench_function(BenchmarkId::from_parameter(id), |b| {
+            b.iter(|| {
+                // Keeps the sense counter of the cell from growing without bound
+                let index = game.index(x, y);
+                game.cells[index].senses = 0;
+                game.distance_sense(x, y).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn update_posterior(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_posterior_ghost_location_probabilities");
+    group.sample_size(10);
+    for (preset, size, id) in variants() {
+        let mut game = game(preset, size);
+        let (x, y) = (size.0 / 2, size.1 / 2);
+        let (color, direction) = game.distance_sense(x, y).unwrap();
+        // Every iteration updates fresh priors, repeating the same reading would drive most cells
+        // to subnormal probabilities
+        group.bench_function(BenchmarkId::from_parameter(id), |b| {
+            b.iter_batched_ref(
+                || game.clone(),
+                |game| game.update_posterior_ghost_location_probabilities(color.clone(), x, y, direction.clone()),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn inference(c: &mut Criterion) {
+    let mut group = c.benchmark_group("inference_observe");
+    group.sample_size(10);
+    for (rows, columns) in SIZES {
+        let mut game = game(Preset::Normal, (rows, columns));
+        let (x, y) = (rows / 2, columns / 2);
+        let (color, direction) = game.distance_sense(x, y).unwrap();
+        let settings = game.settings.clone();
+        let id = format!("{}x{}", rows, columns);
+        group.bench_function(BenchmarkId::new("exact", &id), |b| {
+            b.iter_batched_ref(
+                || ExactGrid::new(&settings),
+                |engine| engine.observe(x, y, &color, &direction),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new(format!("particles-{}", DEFAULT_PARTICLES), &id), |b| {
+            b.iter_batched_ref(
+                || ParticleFilter::new(&settings, DEFAULT_PARTICLES, 1),
+                |engine| engine.observe(x, y, &color, &direction),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// The particle filter reweighs every particle on a reading, and when it resamples it moves each one
+// after weighing it by every reading so far, so both counts matter
+fn particle_filter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("particle_filter_observe");
+    group.sample_size(10);
+    let size = (500, 500);
+    let mut game = game(Preset::Normal, size);
+    let (x, y) = (size.0 / 2, size.1 / 2);
+    let (color, direction) = game.distance_sense(x, y).unwrap();
+    for particles in [1_000, DEFAULT_PARTICLES, 100_000] {
+        for history in [0, 10, 50] {
+            let mut engine = ParticleFilter::new(&game.settings, particles, 1);
+            for k in 0..history {
+                let (hx, hy) = (k * 37 % size.0, k * 53 % size.1);
+                let (color, direction) = game.distance_sense(hx, hy).unwrap();
+                engine.observe(hx, hy, &color, &direction);
+            }
+            let id = format!("{}/{}-readings", particles, history);
+            group.bench_function(BenchmarkId::from_parameter(id), |b| {
+                b.iter_batched_ref(
+                    || engine.clone(),
+                    |engine| engine.observe(x, y, &color, &direction),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+// The solver only handles boards of up to 16 cells, so its benchmarks vary the budget instead
+fn solver(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solver");
+    group.sample_size(10).measurement_time(Duration::from_secs(10));
+    for preset in SENSORS {
+        for (attempts, busts) in [(2, 1), (3, 1), (3, 2)] {
+            let settings = GameSettings {
+                score: attempts,
+                busts,
+                ..settings(preset, (3, 3))
+            };
+            let id = format!("{}/3x3/a{}b{}", preset, attempts, busts);
+            group.bench_function(BenchmarkId::new("solve", &id), |b| {
+                b.iter(|| Solver::new(&settings).unwrap().solve())
+            });
+        }
+        // A query on a belief the memo has not seen yet
+        let settings = GameSettings {
+            score: 3,
+            busts: 2,
+            ..settings(preset, (4, 4))
+        };
+        group.bench_function(BenchmarkId::new("best_action", format!("{}/4x4/a3b2", preset)), |b| {
+            b.iter_batched_ref(
+                || Solver::new(&settings).unwrap(),
+                |solver| {
+                    let belief = solver.update(&solver.uniform_belief(), 1, 1, "yellow", "SE");
+                    solver.best_action(&belief, 3, 2)
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, priors, distance_sense, update_posterior, inference, particle_filter, solver);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+// Built-in strategies, and what every player of a tournament implements
+use crate::game::{Action, Event, Game, GameSettings, Status};
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::cmp::Ordering;
+
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    pub seed: u64,
+    pub won: bool,
+    pub attempts_used: i32,
+    pub busts_used: i32,
+    pub actions: Vec<Action>,
+    // Why the player forfeited the game
+    pub error: Option<String>,
+}
+
+// Greater is better: finishing the game, winning, then using fewer attempts, then fewer busts
+pub fn compare(a: &GameResult, b: &GameResult) -> Ordering {
+    a.error
+        .is_none()
+        .cmp(&b.error.is_none())
+        .then(a.won.cmp(&b.won))
+        .then(b.attempts_used.cmp(&a.attempts_used))
+        .then(b.busts_used.cmp(&a.busts_used))
+}
+
+pub trait Agent {
+    fn name(&self) -> String;
+    // Plays the game of these settings and seed to the end
+    fn play(&mut self, settings: &GameSettings, seed: u64) -> GameResult;
+}
+
+// Plays a game for a strategy that sees the board as a player does: the readings, the budgets
+// and the probabilities they give, but never the ghost
+pub fn play_blind(settings: &GameSettings, seed: u64, mut choose: impl FnMut(&Game) -> Action) -> GameResult {
+    let mut game = Game::from_seed(settings.clone(), seed);
+    game.place_ghost();
+    game.compute_initial_prior_probabilities();
+    // Its seed is not the game's, so the ghost it would place is not the real one
+    let mut view = Game::from_seed(settings.clone(), 0);
+    view.compute_initial_prior_probabilities();
+    let mut actions = vec![];
+    let mut error = None;
+    while game.status == Status::Playing {
+        let action = choose(&view);
+        match game.apply(action) {
+            Ok(Event::Reading { x, y, color, direction }) => view.observe(x, y, &color, &direction),
+            Ok(Event::Bust { x, y, hit: false }) => view.rule_out(x, y),
+            Ok(Event::Bust { .. }) => {}
+            Err(err) => {
+                error = Some(err);
+                break;
+            }
+        }
+        actions.push(action);
+        view.score = game.score;
+        view.busts = game.busts;
+        view.status = game.status;
+    }
+    GameResult {
+        seed,
+        won: game.status == Status::Won,
+        attempts_used: settings.score - game.score,
+        busts_used: settings.busts - game.busts,
+        actions,
+        error,
+    }
+}
+
+fn cells(view: &Game) -> impl Iterator<Item = (i32, i32, f32, bool)> + '_ {
+    view.cells
+        .iter()
+        .zip(&view.belief.probabilities)
+        .map(|(cell, probability)| (cell.x, cell.y, *probability as f32, cell.visited()))
+}
+
+// The most likely cell
+fn best_cell(view: &Game) -> (i32, i32, f32) {
+    cells(view)
+        .map(|(x, y, probability, _)| (x, y, probability))
+        .fold((0, 0, -1.0), |best, cell| if cell.2 > best.2 { cell } else { best })
+}
+
+// Senses random cells and busts a random cell that isn't ruled out when it must
+pub struct RandomAgent {
+    rng: ChaCha8Rng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> RandomAgent {
+        RandomAgent {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn play(&mut self, settings: &GameSettings, seed: u64) -> GameResult {
+        play_blind(settings, seed, |view| {
+            let unvisited = cells(view).filter(|cell| !cell.3).choose(&mut self.rng);
+            match unvisited {
+                // sensing with the last attempt loses the game
+                Some((x, y, _, _)) if view.score > 1 => Action::Sense { x, y },
+                _ => {
+                    let (x, y, _, _) = cells(view)
+                        .filter(|cell| cell.2 > 0.0)
+                        .choose(&mut self.rng)
+                        .unwrap_or((0, 0, 0.0, false));
+                    Action::Bust { x, y }
+                }
+            }
+        })
+    }
+}
+
+// Senses the most likely unvisited cell until a cell is at least `threshold` likely, then busts it
+pub struct GreedyAgent {
+    pub threshold: f32,
+}
+
+impl Agent for GreedyAgent {
+    fn name(&self) -> String {
+        format!("greedy-{}", self.threshold)
+    }
+
+    fn play(&mut self, settings: &GameSettings, seed: u64) -> GameResult {
+        play_blind(settings, seed, |view| self.choose(view))
+    }
+}
+
+// The hint the game gives: what the greedy agent would play from what the player knows, the
+// readings and the missed busts the probabilities already rule out
+pub fn hint(game: &Game) -> Action {
+    GreedyAgent { threshold: 0.5 }.choose(game)
+}
+
+impl GreedyAgent {
+    // The next action on a board seen as a player sees it
+    pub fn choose(&self, view: &Game) -> Action {
+        let (x, y, probability) = best_cell(view);
+        let unvisited = cells(view)
+            .filter(|cell| !cell.3)
+            .fold(None::<(i32, i32, f32, bool)>, |best, cell| match best {
+                Some(best) if best.2 >= cell.2 => Some(best),
+                _ => Some(cell),
+            });
+        match unvisited {
+            Some((x, y, _, _)) if probability < self.threshold && view.score > 1 => Action::Sense { x, y },
+            _ => Action::Bust { x, y },
+        }
+    }
+}
+
+// The agents `builtin` knows
+pub const BUILTIN: [&str; 3] = ["random", "greedy", "cautious"];
+
+pub fn builtin(name: &str) -> Option<Box<dyn Agent>> {
+    match name {
+        "random" => Some(Box::new(RandomAgent::new(0))),
+        "greedy" => Some(Box::new(GreedyAgent { threshold: 0.5 })),
+        "cautious" => Some(Box::new(GreedyAgent { threshold: 0.9 })),
+        _ => None,
+    }
+}
@@ -0,0 +1,112 @@
+// The REST API bots play through, see docs/api.md for the JSON schemas
+use crate::game::{Action, Event, Game, GameSettings, Status};
+use crate::game_store;
+use crate::online::{Reveal, Turn};
+use crate::protocol::NewGame;
+use actix_web::http::StatusCode;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+// The largest board the API plays on, smaller than the web game's as bots start games by the hundred
+pub const MAX_API_BOARD_SIZE: i32 = 100;
+
+#[derive(Deserialize)]
+pub struct Target {
+    pub x: i32,
+    pub y: i32,
+}
+
+// What anyone may know about a game: never the ghost or the seed until the game is over
+#[derive(Serialize)]
+pub struct GameState {
+    pub id: String,
+    pub settings: GameSettings,
+    pub score: i32,
+    pub busts: i32,
+    pub status: Status,
+    pub reveal: Option<Reveal>,
+}
+
+impl GameState {
+    fn new(id: &str, game: &Game) -> GameState {
+        GameState {
+            id: id.to_string(),
+            settings: game.settings.clone(),
+            score: game.score,
+            busts: game.busts,
+            status: game.status,
+            reveal: (game.status != Status::Playing).then_some(Reveal {
+                seed: game.seed,
+                ghost_position: game.ghost_position,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct GameHistory {
+    #[serde(flatten)]
+    pub state: GameState,
+    pub history: Vec<Event>,
+}
+
+fn error(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(serde_json::json!({ "error": message }))
+}
+
+fn not_found() -> HttpResponse {
+    error(StatusCode::NOT_FOUND, "No such game, it may have expired")
+}
+
+#[post("/api/games")]
+async fn create_game(request: HttpRequest, body: web::Json<NewGame>) -> HttpResponse {
+    let settings = match body.settings() {
+        Ok(settings) => settings,
+        Err(err) => return error(StatusCode::BAD_REQUEST, &err),
+    };
+    if settings.width > MAX_API_BOARD_SIZE || settings.height > MAX_API_BOARD_SIZE {
+        let message = format!("The board can be at most {0}x{0}", MAX_API_BOARD_SIZE);
+        return error(StatusCode::BAD_REQUEST, &message);
+    }
+    let mut game = Game::from_seed(settings, body.seed.unwrap_or_else(rand::random));
+    game.place_ghost();
+    game.compute_initial_prior_probabilities();
+    let id = match game_store::insert(game, game_store::Ranking::Unranked, &game_store::client(&request)) {
+        Ok(id) => id,
+        Err(err) => return error(StatusCode::TOO_MANY_REQUESTS, &err),
+    };
+    game_store::with_game(&id, |game, _| GameState::new(&id, game))
+        .map_or_else(not_found, |state| HttpResponse::Created().json(state))
+}
+
+#[get("/api/games/{id}")]
+async fn get_game(id: web::Path<String>) -> HttpResponse {
+    game_store::with_game(&id, |game, history| GameHistory {
+        state: GameState::new(&id, game),
+        history: history.to_vec(),
+    })
+    .map_or_else(not_found, |history| HttpResponse::Ok().json(history))
+}
+
+fn play(id: &str, action: Action) -> HttpResponse {
+    match game_store::play(id, action, Turn::new) {
+        Some(Ok(turn)) => HttpResponse::Ok().json(turn),
+        Some(Err(err)) => error(StatusCode::BAD_REQUEST, &err),
+        None => not_found(),
+    }
+}
+
+#[post("/api/games/{id}/sense")]
+async fn sense(id: web::Path<String>, target: web::Json<Target>) -> HttpResponse {
+    play(&id, Action::Sense { x: target.x, y: target.y })
+}
+
+#[post("/api/games/{id}/bust")]
+async fn bust(id: web::Path<String>, target: web::Json<Target>) -> HttpResponse {
+    play(&id, Action::Bust { x: target.x, y: target.y })
+}
+
+// Registered before the server functions, which take every other path under /api
+pub fn routes(config: &mut web::ServiceConfig) {
+    config.service(create_game).service(get_game).service(sense).service(bust);
+}
@@ -1,4 +1,9 @@
-use crate::index::GameView;
+use crate::i18n::{self, Locale};
+use crate::index::{GameView, Mode};
+use crate::leaderboard_view::LeaderboardView;
+use crate::palette::{self, Appearance};
+use crate::stats_view::StatsView;
+use crate::tutorial_view::TutorialView;
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
@@ -8,19 +13,50 @@ pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
 
+    // The look the player picked, for every page. Local storage is only read once mounted, so that
+    // the server and the hydrating client render the same page.
+    let appearance = create_rw_signal(Appearance::default());
+    provide_context(appearance);
+    create_effect(move |_| appearance.set(palette::load()));
+    // The same goes for the language, English until then
+    let locale = create_rw_signal(Locale::default());
+    provide_context(locale);
+    create_effect(move |_| locale.set(i18n::load()));
+
+    // A static CSR build (`trunk serve`, GitHub Pages) can be served from a sub path that none of
+    // the routes match, so it falls back to the game. With a server, unknown paths are a real 404.
+    #[cfg(feature = "csr")]
+    let fallback = || view! { <GameView/> };
+    #[cfg(not(feature = "csr"))]
+    let fallback = NotFound;
+
     view! {
         // injects a stylesheet into the document <head>
         // id=leptos means cargo-leptos will hot-reload this stylesheet
+        // Trunk links the stylesheet from index.html instead
+        {(!cfg!(feature = "csr")).then(|| view! { <Stylesheet id="leptos" href="/pkg/bust-ghost.css"/> })}
 
         // sets the document title
         <Title text="Bust The Ghost"/>
+        <Html
+            lang=move || locale.get().name()
+            dir=move || locale.get().dir()
+            attr:data-theme=move || appearance.with(|appearance| appearance.theme.name())
+        />
 
         // content for this welcome page
         <Router>
             <main>
                 <Routes>
-                    <Route path="/*any" view=GameView/>
-                    // <Route path="/*any" view=NotFound/>
+                    <Route path="/" view=|| view! { <GameView/> }/>
+                    <Route path="/stats" view=StatsView/>
+                    <Route path="/leaderboard" view=LeaderboardView/>
+                    <Route path="/tutorial" view=TutorialView/>
+                    <Route path="/play" view=|| view! { <GameView mode=Mode::Link/> }/>
+                    <Route path="/daily" view=|| view! { <GameView mode=Mode::Daily/> }/>
+                    <Route path="/online" view=|| view! { <GameView mode=Mode::Online/> }/>
+                    <Route path="/online/daily" view=|| view! { <GameView mode=Mode::RankedDaily/> }/>
+                    <Route path="/*any" view=fallback/>
                 </Routes>
             </main>
         </Router>
@@ -0,0 +1,145 @@
+// Runs bot executables that speak the line protocol (see protocol.rs) on the same seeds, with the
+// referee playing the engine's side.
+use crate::agents::{Agent, GameResult};
+use crate::game::{Game, GameSettings, Status};
+use crate::protocol::{self, Command, Session};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command as Process, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+// A bot that keeps sending commands without finishing its game forfeits it
+const MAX_COMMANDS_PER_GAME: usize = 100_000;
+
+pub struct Bot {
+    pub command: String,
+    // How long the bot may think about each command
+    pub timeout: Duration,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Bot {
+    // `command` is split on whitespace into the program and its arguments
+    pub fn spawn(command: &str, timeout: Duration) -> Result<Bot, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("Empty bot command")?;
+        let mut child = Process::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("Could not start '{}': {}", command, err))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        // Read on a thread so that a silent bot can time out
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Bot {
+            command: command.to_string(),
+            timeout,
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("Could not write to the bot: {}", err))
+    }
+
+    fn receive(&self) -> Result<String, String> {
+        self.lines.recv_timeout(self.timeout).map_err(|err| match err {
+            mpsc::RecvTimeoutError::Timeout => format!("No command within {:?}", self.timeout),
+            mpsc::RecvTimeoutError::Disconnected => "The bot exited".to_string(),
+        })
+    }
+}
+
+impl Agent for Bot {
+    fn name(&self) -> String {
+        self.command.clone()
+    }
+
+    // Plays one game with the referee's settings and seed, whatever the bot asked for in `newgame`
+    fn play(&mut self, settings: &GameSettings, seed: u64) -> GameResult {
+        let mut session = Session::default();
+        let mut actions = vec![];
+        let mut error = None;
+        for _ in 0..MAX_COMMANDS_PER_GAME {
+            let line = match self.receive() {
+                Ok(line) => line,
+                Err(err) => {
+                    error = Some(err);
+                    break;
+                }
+            };
+            let reply = match protocol::parse(&line) {
+                Ok(Command::IsReady) => "readyok".to_string(),
+                Ok(Command::NewGame(_)) if session.game.is_some() => "error Finish the current game first".to_string(),
+                Ok(Command::NewGame(_)) => session.start(Game::from_seed(settings.clone(), seed)),
+                Ok(Command::Play(action)) => {
+                    let reply = session.play(action);
+                    if !reply.starts_with("error") {
+                        actions.push(action);
+                    }
+                    reply
+                }
+                Ok(Command::Quit) => {
+                    error = Some("The bot quit".to_string());
+                    break;
+                }
+                Err(err) => format!("error {}", err),
+            };
+            if let Err(err) = self.send(&reply) {
+                error = Some(err);
+                break;
+            }
+            if finished(&session) {
+                break;
+            }
+        }
+        if error.is_none() && !finished(&session) {
+            error = Some(format!("Still playing after {} commands", MAX_COMMANDS_PER_GAME));
+        }
+        let game = session.game.as_ref();
+        GameResult {
+            seed,
+            won: session.status() == Some(Status::Won),
+            attempts_used: game.map_or(0, |game| game.settings.score - game.score),
+            busts_used: game.map_or(0, |game| game.settings.busts - game.busts),
+            actions,
+            error,
+        }
+    }
+}
+
+fn finished(session: &Session) -> bool {
+    matches!(session.status(), Some(Status::Won | Status::Lost))
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        // The match is over
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+// Plays every seed with each bot, the results are in the order of the seeds
+pub fn run(bots: &mut [Bot], settings: &GameSettings, seeds: &[u64]) -> Vec<Vec<GameResult>> {
+    seeds
+        .iter()
+        .map(|seed| bots.iter_mut().map(|bot| bot.play(settings, *seed)).collect())
+        .collect()
+}
@@ -0,0 +1,383 @@
+// Plays Bust The Ghost in the terminal, or serves the bot protocol (see docs/protocol.md).
+// to run: `cargo run --bin bust-ghost-cli -- --preset hard`
+use bust_ghost::agents::{self, Agent, GameResult};
+use bust_ghost::arena::{self, Bot};
+use bust_ghost::game::*;
+use bust_ghost::preset::Preset;
+use bust_ghost::protocol::{self, Command, Session};
+use bust_ghost::scores;
+use bust_ghost::solver::Solver;
+use bust_ghost::tournament;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: bust-ghost-cli [--preset <easy|normal|hard|nightmare>] [--list-presets]
+       bust-ghost-cli engine
+       bust-ghost-cli arena [--preset <name>] [--games <n>] [--seed <n>] [--timeout-ms <n>] <bot command> <bot command>
+       bust-ghost-cli tournament [--preset <name>] [--games <n>] [--seed <n>] [--timeout-ms <n>] [--out <path>]
+                                 [--agent <random|greedy|cautious>]... [--bot <command>]...
+       bust-ghost-cli solve [--preset <name>] [--width <n>] [--height <n>] [--attempts <n>] [--busts <n>] [--games <n>]";
+
+fn main() {
+    let mut preset = Preset::Normal;
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("engine") => return engine(),
+        Some("arena") => {
+            args.next();
+            return arena(args);
+        }
+        Some("tournament") => {
+            args.next();
+            return run_tournament(args);
+        }
+        Some("solve") => {
+            args.next();
+            return solve(args);
+        }
+        _ => {}
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preset" | "-p" => match args.next().map(|name| name.parse::<Preset>()) {
+                Some(Ok(parsed)) => preset = parsed,
+                Some(Err(err)) => exit_with(&err),
+                None => exit_with(USAGE),
+            },
+            "--list-presets" => {
+                for preset in Preset::ALL {
+                    let settings = preset.settings();
+                    println!(
+                        "{:<10} {}x{} board, {} attempts, {} busts, hints {:.0}% reliable{}",
+                        preset.name(),
+                        settings.width,
+                        settings.height,
+                        settings.score,
+                        settings.busts,
+                        settings.direction_reliability * 100.0,
+                        if settings.allow_peep { ", peeping allowed" } else { "" }
+                    );
+                }
+                return;
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => exit_with(USAGE),
+        }
+    }
+    play(preset);
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn parse_arg<T: std::str::FromStr>(value: Option<String>) -> T {
+    value.and_then(|value| value.parse().ok()).unwrap_or_else(|| exit_with(USAGE))
+}
+
+// Serves the line protocol on stdin and stdout
+fn engine() {
+    let mut session = Session::default();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let reply = match protocol::parse(&line) {
+            Ok(Command::IsReady) => "readyok".to_string(),
+            Ok(Command::NewGame(new_game)) => match new_game.game() {
+                Ok(game) => session.start(game),
+                Err(err) => format!("error {}", err),
+            },
+            Ok(Command::Play(action)) => session.play(action),
+            Ok(Command::Quit) => return,
+            Err(err) => format!("error {}", err),
+        };
+        writeln!(stdout, "{}", reply).and_then(|_| stdout.flush()).ok();
+    }
+}
+
+// Plays two bots on the same seeds and compares them
+fn arena(mut args: impl Iterator<Item = String>) {
+    let mut preset = Preset::Normal;
+    let mut games = 10;
+    let mut seed = rand::random::<u64>();
+    let mut timeout = Duration::from_secs(5);
+    let mut commands = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preset" | "-p" => preset = parse_arg(args.next()),
+            "--games" | "-n" => games = parse_arg(args.next()),
+            "--seed" => seed = parse_arg(args.next()),
+            "--timeout-ms" => timeout = Duration::from_millis(parse_arg(args.next())),
+            _ => commands.push(arg),
+        }
+    }
+    if commands.len() != 2 {
+        exit_with(USAGE);
+    }
+    let mut bots = commands
+        .iter()
+        .map(|command| Bot::spawn(command, timeout).unwrap_or_else(|err| exit_with(&err)))
+        .collect::<Vec<_>>();
+    let seeds = (0..games).map(|i| seed.wrapping_add(i)).collect::<Vec<_>>();
+    let results = arena::run(&mut bots, &preset.settings(), &seeds);
+
+    let describe = |result: &GameResult| match &result.error {
+        Some(err) => format!("forfeit ({})", err),
+        None => format!(
+            "{} with {} attempts, {} busts",
+            if result.won { "won" } else { "lost" },
+            result.attempts_used,
+            result.busts_used
+        ),
+    };
+    let mut better = [0, 0];
+    for games in &results {
+        println!("seed {}", games[0].seed);
+        for (bot, result) in bots.iter().zip(games) {
+            println!("  {:<30} {}", bot.command, describe(result));
+        }
+        match agents::compare(&games[0], &games[1]) {
+            std::cmp::Ordering::Greater => better[0] += 1,
+            std::cmp::Ordering::Less => better[1] += 1,
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+    println!();
+    println!(
+        "{} game{} on {}, starting from seed {}",
+        games,
+        if games == 1 { "" } else { "s" },
+        preset.label(),
+        seed
+    );
+    for (i, bot) in bots.iter().enumerate() {
+        let played = results.iter().map(|games| &games[i]).collect::<Vec<_>>();
+        let wins = played.iter().filter(|result| result.won).count();
+        let attempts = played.iter().map(|result| result.attempts_used).sum::<i32>();
+        println!(
+            "{:<30} {} wins, {:.1} attempts per game, better on {} seeds",
+            bot.command,
+            wins,
+            attempts as f64 / played.len().max(1) as f64,
+            better[i]
+        );
+    }
+}
+
+fn play(preset: Preset) {
+    let mut game = Game::from_settings(preset.settings());
+    game.place_ghost();
+    game.compute_initial_prior_probabilities();
+    println!("Bust The Ghost - {} ({}x{})", preset.label(), game.columns(), game.rows());
+    println!("Commands: sense <x> <y>, bust <x> <y>, peep, board, quit");
+    if let Some(best) = load_high_scores().get(preset.name()) {
+        println!("High score: {} attempts left", best);
+    }
+
+    let mut peeping = false;
+    let stdin = io::stdin();
+    print_board(&game, peeping);
+    prompt(&game);
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            [command @ ("sense" | "bust"), x, y] => match parse_cell(&game, x, y) {
+                Some((x, y)) => {
+                    let action = if *command == "sense" { Action::Sense { x, y } } else { Action::Bust { x, y } };
+                    match game.apply(action) {
+                        Ok(Event::Reading { color, direction, .. }) => println!("{} {}", color, direction),
+                        Ok(Event::Bust { hit: false, .. }) => println!("Missed!"),
+                        Ok(Event::Bust { hit: true, .. }) => println!("You win!"),
+                        Err(err) => println!("{}", err),
+                    }
+                }
+                None => println!("No such cell"),
+            },
+            ["peep"] if game.settings.allow_peep => {
+                peeping = !peeping;
+                print_board(&game, peeping);
+            }
+            ["peep"] => println!("Peeping is not allowed on {}", preset.label()),
+            ["board"] => print_board(&game, peeping),
+            ["quit"] => return,
+            [] => {}
+            _ => println!("Commands: sense <x> <y>, bust <x> <y>, peep, board, quit"),
+        }
+        match game.status {
+            Status::Playing => prompt(&game),
+            Status::Won => {
+                record_win(preset, game.score);
+                return;
+            }
+            Status::Lost => {
+                println!("Out of {}! You lose!", if game.busts == 0 { "busts" } else { "attempts" });
+                return;
+            }
+        }
+    }
+}
+
+// High scores are kept in `~/.bust-ghost-high-scores.json`, the terminal has no local storage
+fn high_scores_path() -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    home.join(".bust-ghost-high-scores.json")
+}
+
+fn load_high_scores() -> HashMap<String, i32> {
+    std::fs::read_to_string(high_scores_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn record_win(preset: Preset, score: i32) {
+    let mut high_scores = load_high_scores();
+    if !scores::beat(&mut high_scores, preset, score) {
+        println!("High score on {}: {} attempts left", preset.label(), high_scores[preset.name()]);
+        return;
+    }
+    println!("New high score on {}: {} attempts left", preset.label(), score);
+    let path = high_scores_path();
+    let json = serde_json::to_string(&high_scores).expect("high scores serialize");
+    if let Err(err) = std::fs::write(&path, json) {
+        eprintln!("Could not save high scores to {}: {}", path.display(), err);
+    }
+}
+
+fn parse_cell(game: &Game, x: &str, y: &str) -> Option<(i32, i32)> {
+    let x = x.parse::<i32>().ok()?;
+    let y = y.parse::<i32>().ok()?;
+    if (0..game.rows()).contains(&x) && (0..game.columns()).contains(&y) {
+        Some((x, y))
+    } else {
+        None
+    }
+}
+
+fn prompt(game: &Game) {
+    print!("[{} attempts, {} busts] > ", game.score, game.busts);
+    io::stdout().flush().ok();
+}
+
+fn print_board(game: &Game, peeping: bool) {
+    let columns = game.columns() as usize;
+    for (row, probabilities) in game.cells.chunks(columns).zip(game.belief.probabilities.chunks(columns)) {
+        let line = row
+            .iter()
+            .zip(probabilities)
+            .map(|(cell, probability)| {
+                if peeping {
+                    format!("{:>6.2}", probability * 100.0)
+                } else {
+                    match cell.color {
+                        Some(SensorColor::Green) => " G",
+                        Some(SensorColor::Yellow) => " Y",
+                        Some(SensorColor::Orange) => " O",
+                        Some(SensorColor::Red) => " R",
+                        None => " .",
+                    }
+                    .to_string()
+                }
+            })
+            .collect::<String>();
+        println!("{}", line);
+    }
+}
+
+// Rates built-in agents and protocol bots on the same boards, and writes the report as
+// `<out>.json` and `<out>.md`
+fn run_tournament(mut args: impl Iterator<Item = String>) {
+    let mut preset = Preset::Normal;
+    let mut games = 100;
+    let mut seed = rand::random::<u64>();
+    let mut timeout = Duration::from_secs(5);
+    let mut out = "tournament".to_string();
+    let mut agent_names = vec![];
+    let mut bot_commands = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preset" | "-p" => preset = parse_arg(args.next()),
+            "--games" | "-n" => games = parse_arg(args.next()),
+            "--seed" => seed = parse_arg(args.next()),
+            "--timeout-ms" => timeout = Duration::from_millis(parse_arg(args.next())),
+            "--out" | "-o" => out = parse_arg(args.next()),
+            "--agent" => agent_names.push(parse_arg::<String>(args.next())),
+            "--bot" => bot_commands.push(parse_arg::<String>(args.next())),
+            _ => exit_with(USAGE),
+        }
+    }
+    if agent_names.is_empty() && bot_commands.is_empty() {
+        agent_names = agents::BUILTIN.iter().map(|name| name.to_string()).collect();
+    }
+    let mut players = agent_names
+        .iter()
+        .map(|name| {
+            agents::builtin(name).unwrap_or_else(|| {
+                exit_with(&format!("Unknown agent '{}', expected one of {}", name, agents::BUILTIN.join(", ")))
+            })
+        })
+        .collect::<Vec<_>>();
+    for command in &bot_commands {
+        players.push(Box::new(Bot::spawn(command, timeout).unwrap_or_else(|err| exit_with(&err))) as Box<dyn Agent>);
+    }
+
+    let seeds = (0..games).map(|i| seed.wrapping_add(i)).collect::<Vec<_>>();
+    let report = tournament::run(&mut players, preset, &seeds);
+    let markdown = report.to_markdown();
+    for (path, contents) in [(format!("{}.json", out), report.to_json()), (format!("{}.md", out), markdown.clone())] {
+        std::fs::write(&path, contents).unwrap_or_else(|err| exit_with(&format!("Could not write {}: {}", path, err)));
+    }
+    print!("{}", markdown);
+}
+
+// Prints the optimal chance of winning a small board, and how often the built-in agents win it
+fn solve(mut args: impl Iterator<Item = String>) {
+    let mut settings = GameSettings {
+        width: 3,
+        height: 3,
+        score: 4,
+        busts: 2,
+        ..Preset::Normal.settings()
+    };
+    let mut games = 1000;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preset" | "-p" => {
+                let preset = parse_arg::<Preset>(args.next()).settings();
+                settings = GameSettings {
+                    width: settings.width,
+                    height: settings.height,
+                    score: settings.score,
+                    busts: settings.busts,
+                    ..preset
+                };
+            }
+            "--width" => settings.width = parse_arg(args.next()),
+            "--height" => settings.height = parse_arg(args.next()),
+            "--attempts" => settings.score = parse_arg(args.next()),
+            "--busts" => settings.busts = parse_arg(args.next()),
+            "--games" | "-n" => games = parse_arg(args.next()),
+            _ => exit_with(USAGE),
+        }
+    }
+    settings.preset = None;
+    let mut solver = Solver::new(&settings).unwrap_or_else(|err| exit_with(&err));
+    let solution = solver.solve();
+    println!(
+        "{}x{} board, {} attempts, {} busts: optimal win probability {:.4}, starting with {:?} ({} beliefs)",
+        settings.width, settings.height, settings.score, settings.busts, solution.win_probability, solution.first_action, solution.states
+    );
+    for name in agents::BUILTIN {
+        let mut agent = agents::builtin(name).expect("built-in agents exist");
+        let wins = (0..games).filter(|seed| agent.play(&settings, *seed).won).count();
+        println!("{:<12} won {:.4} of {} games", agent.name(), wins as f64 / games.max(1) as f64, games);
+    }
+}
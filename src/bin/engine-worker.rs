@@ -0,0 +1,13 @@
+// The engine worker of `bust_ghost::worker`, which Trunk builds next to the app (see index.html)
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use bust_ghost::worker::{EngineWorker, Json};
+    use gloo::worker::Registrable;
+
+    console_error_panic_hook::set_once();
+    EngineWorker::registrar().encoding::<Json>().register();
+}
+
+// Only the browser runs workers
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {}
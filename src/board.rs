@@ -0,0 +1,104 @@
+use crate::game::{Game, SensorColor};
+use leptos::*;
+
+// A cell of the board as signals of its own, so that a reading only re-renders the cells whose
+// color or probability changed
+#[derive(Clone, Copy)]
+pub struct CellSignals {
+    pub x: i32,
+    pub y: i32,
+    pub color: RwSignal<Option<SensorColor>>,
+    pub probability: RwSignal<f64>,
+}
+
+// The board of a `Game` as one signal per cell. `cells` is only replaced when the size of the
+// board changes, any other change of the game only sets the signals of the cells it touched.
+// Boards of more than `max_cells` cells get no cell signals, they are drawn from the game itself.
+#[derive(Clone, Copy)]
+pub struct BoardSignals {
+    pub rows: RwSignal<i32>,
+    pub columns: RwSignal<i32>,
+    pub cells: RwSignal<Vec<CellSignals>>,
+    // The cell signals are created under the component and not under whatever runs `sync`, which
+    // would dispose of them the next time it runs
+    owner: Option<Owner>,
+    max_cells: usize,
+}
+
+// The name of the column `y` as the board headers show it: A to Z, then AA, AB and so on
+pub fn column_label(y: i32) -> String {
+    let mut label = vec![];
+    let mut y = y + 1;
+    while y > 0 {
+        y -= 1;
+        label.push(b'A' + (y % 26) as u8);
+        y /= 26;
+    }
+    label.reverse();
+    String::from_utf8(label).unwrap_or_default()
+}
+
+// The name of the cell (x, y) as it is read out, its column then its row from 1: "C4"
+pub fn cell_label(x: i32, y: i32) -> String {
+    format!("{}{}", column_label(y), x + 1)
+}
+
+fn cell_signals(game: &Game, max_cells: usize) -> Vec<CellSignals> {
+    if game.cells.len() > max_cells {
+        return vec![];
+    }
+    game.cells
+        .iter()
+        .zip(&game.belief.probabilities)
+        .map(|(cell, probability)| CellSignals {
+            x: cell.x,
+            y: cell.y,
+            color: create_rw_signal(cell.color),
+            probability: create_rw_signal(*probability),
+        })
+        .collect()
+}
+
+impl BoardSignals {
+    pub fn new(game: &Game, max_cells: usize) -> BoardSignals {
+        BoardSignals {
+            rows: create_rw_signal(game.rows()),
+            columns: create_rw_signal(game.columns()),
+            cells: create_rw_signal(cell_signals(game, max_cells)),
+            owner: Owner::current(),
+            max_cells,
+        }
+    }
+
+    // Copies the game into the signals, only setting the ones whose value changed
+    pub fn sync(&self, game: &Game) {
+        if (self.rows.get_untracked(), self.columns.get_untracked()) != (game.rows(), game.columns()) {
+            let cells = match self.owner {
+                Some(owner) => with_owner(owner, || cell_signals(game, self.max_cells)),
+                None => cell_signals(game, self.max_cells),
+            };
+            // the cells never disagree with the size of the board
+            batch(|| {
+                self.rows.set(game.rows());
+                self.columns.set(game.columns());
+                self.cells.set(cells);
+            });
+            return;
+        }
+        self.cells.with_untracked(|cells| {
+            for ((signals, cell), probability) in cells.iter().zip(&game.cells).zip(&game.belief.probabilities) {
+                if signals.color.get_untracked() != cell.color {
+                    signals.color.set(cell.color);
+                }
+                if signals.probability.get_untracked() != *probability {
+                    signals.probability.set(*probability);
+                }
+            }
+        });
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> CellSignals {
+        let columns = self.columns.get();
+        self.cells.with(|cells| cells[(x * columns + y) as usize])
+    }
+}
@@ -0,0 +1,431 @@
+use crate::board::cell_label;
+use crate::game::Game;
+use crate::i18n::{self, t, tr, use_locale};
+use crate::palette::{glyph, Appearance, Palette};
+use leptos::html::{Canvas, Div};
+use leptos::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+// Boards with more cells than this are drawn on a canvas instead of with a button per cell
+pub const CANVAS_ABOVE: i32 = 10_000;
+// Size in pixels of a cell, zoomed all the way out and all the way in
+const MIN_CELL_SIZE: f64 = 0.25;
+const MAX_CELL_SIZE: f64 = 80.0;
+// Cells need to be at least this big for the grid lines, their glyph and their probability
+const GRID_LINES_FROM: f64 = 6.0;
+const GLYPHS_FROM: f64 = 16.0;
+const TEXT_FROM: f64 = 40.0;
+// How far a pointer can move between pressing and releasing it for it to be a click
+const CLICK_SLOP: f64 = 4.0;
+const HEATMAP: [u8; 3] = [29, 78, 216];
+
+// One pixel per cell, with the heatmap of the probabilities over the colors when it is shown: the
+// likelier the ghost is in a cell compared to the likeliest cell, the bluer the cell
+fn pixels(game: &Game, heatmap: bool, palette: Palette) -> Vec<u8> {
+    let most_likely = game.belief.probabilities.iter().cloned().fold(0.0, f64::max);
+    let mut pixels = Vec::with_capacity(game.cells.len() * 4);
+    for (cell, probability) in game.cells.iter().zip(&game.belief.probabilities) {
+        let base = palette.rgb(cell.color);
+        let heat = if heatmap && most_likely > 0.0 { 0.8 * probability / most_likely } else { 0.0 };
+        for (base, over) in base.iter().zip(HEATMAP) {
+            pixels.push((*base as f64 * (1.0 - heat) + over as f64 * heat).round() as u8);
+        }
+        pixels.push(255);
+    }
+    pixels
+}
+
+fn context(canvas: &HtmlCanvasElement) -> Option<CanvasRenderingContext2d> {
+    canvas.get_context("2d").ok().flatten()?.dyn_into::<CanvasRenderingContext2d>().ok()
+}
+
+// Where the board is drawn: the size of a cell and the scroll of the board, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Camera {
+    cell_size: f64,
+    left: f64,
+    top: f64,
+}
+
+impl Camera {
+    // The whole board, centered in a canvas of `width` x `height` pixels
+    fn fit(rows: i32, columns: i32, width: f64, height: f64) -> Camera {
+        let cell_size = (width / columns as f64).min(height / rows as f64).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+        Camera {
+            cell_size,
+            left: (columns as f64 * cell_size - width) / 2.0,
+            top: (rows as f64 * cell_size - height) / 2.0,
+        }
+    }
+
+    // The cell under the point (px, py) of the canvas
+    fn cell_at(&self, px: f64, py: f64) -> (i32, i32) {
+        (
+            ((py + self.top) / self.cell_size).floor() as i32,
+            ((px + self.left) / self.cell_size).floor() as i32,
+        )
+    }
+
+    // Zooms by `factor` keeping the point (px, py) of the canvas over the same spot of the board
+    fn zoom(&self, factor: f64, px: f64, py: f64) -> Camera {
+        let cell_size = (self.cell_size * factor).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+        let scale = cell_size / self.cell_size;
+        Camera {
+            cell_size,
+            left: (self.left + px) * scale - px,
+            top: (self.top + py) * scale - py,
+        }
+    }
+}
+
+// Draws the board on a canvas, for boards too big for a button per cell. Drag to pan, scroll or pinch
+// to zoom, click or tap a cell to select it.
+#[component]
+pub fn CanvasBoard<F>(
+    game: ReadSignal<Game>,
+    selected: ReadSignal<(i32, i32)>,
+    show_probabilities: Memo<bool>,
+    appearance: ReadSignal<Appearance>,
+    on_select: F,
+) -> impl IntoView
+where
+    F: Fn(i32, i32) + Copy + 'static,
+{
+    let locale = use_locale();
+    let container = create_node_ref::<Div>();
+    let canvas = create_node_ref::<Canvas>();
+    // The board at one pixel per cell, scaled up when drawn
+    let image = store_value(None::<HtmlCanvasElement>);
+    let (image_version, set_image_version) = create_signal(0);
+    let (camera, set_camera) = create_signal(None::<Camera>);
+    // The pointers pressed on the canvas and where each is on it, a mouse or one finger drags and
+    // two fingers pinch
+    let pointers = store_value(Vec::<(i32, f64, f64)>::new());
+    // Where the pointer was pressed and the camera then, while dragging
+    let drag = store_value(None::<(f64, f64, Camera)>);
+    // Whether the pointers moved too far since they were pressed for a click
+    let moved = store_value(false);
+
+    let canvas_size = move || canvas.get_untracked().map(|canvas| (canvas.width() as f64, canvas.height() as f64));
+    let fit = move || {
+        if let Some((width, height)) = canvas_size() {
+            let (rows, columns) = game.with_untracked(|game| (game.rows(), game.columns()));
+            set_camera.set(Some(Camera::fit(rows, columns, width, height)));
+        }
+    };
+    let resize = move || {
+        if let (Some(container), Some(canvas)) = (container.get_untracked(), canvas.get_untracked()) {
+            canvas.set_width(container.client_width().max(1) as u32);
+            canvas.set_height(container.client_height().max(1) as u32);
+            if camera.get_untracked().is_none() {
+                fit();
+            } else {
+                set_camera.update(|_| {});
+            }
+        }
+    };
+    create_effect(move |_| {
+        if canvas.get().is_some() {
+            resize();
+        }
+    });
+    let resize_listener = window_event_listener(ev::resize, move |_| resize());
+    on_cleanup(move || resize_listener.remove());
+
+    // Redraws the image when the game or the heatmap changes, and fits a board of another size
+    create_effect(move |size: Option<(i32, i32)>| {
+        let show = show_probabilities.get();
+        let palette = appearance.with(|appearance| appearance.palette);
+        let new_size = game.with(|game| {
+            let (rows, columns) = (game.rows(), game.columns());
+            let Ok(data) = ImageData::new_with_u8_clamped_array_and_sh(
+                Clamped(&pixels(game, show, palette)),
+                columns as u32,
+                rows as u32,
+            ) else {
+                return (rows, columns);
+            };
+            let element = image.get_value().unwrap_or_else(|| document().create_element("canvas").unwrap().unchecked_into());
+            element.set_width(columns as u32);
+            element.set_height(rows as u32);
+            if let Some(context) = context(&element) {
+                context.put_image_data(&data, 0.0, 0.0).ok();
+            }
+            image.set_value(Some(element));
+            (rows, columns)
+        });
+        if size.is_some_and(|size| size != new_size) {
+            fit();
+        }
+        set_image_version.update(|version| *version += 1);
+        new_size
+    });
+
+    create_effect(move |_| {
+        image_version.track();
+        let locale = locale.get();
+        let (Some(camera), Some(canvas), Some(image)) = (camera.get(), canvas.get(), image.get_value()) else {
+            return;
+        };
+        let Some(context) = context(&canvas) else {
+            return;
+        };
+        let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+        let (rows, columns) = game.with_untracked(|game| (game.rows(), game.columns()));
+        let size = camera.cell_size;
+        context.set_fill_style(&"#e5e7eb".into());
+        context.fill_rect(0.0, 0.0, width, height);
+        context.set_image_smoothing_enabled(false);
+        context
+            .draw_image_with_html_canvas_element_and_dw_and_dh(
+                &image,
+                -camera.left,
+                -camera.top,
+                columns as f64 * size,
+                rows as f64 * size,
+            )
+            .ok();
+
+        // only the cells in view get lines and text
+        let (first_row, first_column) = camera.cell_at(0.0, 0.0);
+        let (last_row, last_column) = camera.cell_at(width, height);
+        let (first_row, first_column) = (first_row.max(0), first_column.max(0));
+        let (last_row, last_column) = (last_row.min(rows - 1), last_column.min(columns - 1));
+        if size >= GRID_LINES_FROM {
+            context.set_stroke_style(&"black".into());
+            context.set_line_width(1.0);
+            context.begin_path();
+            for x in first_row..=last_row + 1 {
+                let y = x as f64 * size - camera.top;
+                context.move_to(first_column as f64 * size - camera.left, y);
+                context.line_to((last_column + 1) as f64 * size - camera.left, y);
+            }
+            for y in first_column..=last_column + 1 {
+                let x = y as f64 * size - camera.left;
+                context.move_to(x, first_row as f64 * size - camera.top);
+                context.line_to(x, (last_row + 1) as f64 * size - camera.top);
+            }
+            context.stroke();
+        }
+        let text = size >= TEXT_FROM && show_probabilities.get_untracked();
+        let Appearance { palette, glyphs, .. } = appearance.get_untracked();
+        let glyphs = glyphs && size >= GLYPHS_FROM;
+        if text || glyphs {
+            context.set_text_align("center");
+            context.set_text_baseline("middle");
+            game.with_untracked(|game| {
+                for x in first_row..=last_row {
+                    for y in first_column..=last_column {
+                        let color = game.cell(x, y).color;
+                        let center = ((y as f64 + 0.5) * size - camera.left, (x as f64 + 0.5) * size - camera.top);
+                        context.set_fill_style(&palette.text(color).into());
+                        // the glyph moves up to make room for the probability
+                        if glyphs && color.is_some() {
+                            let top = if text { center.1 - size / 4.0 } else { center.1 };
+                            context.set_font(&format!("{}px sans-serif", (size / 2.5).round()));
+                            context.fill_text(&glyph(color).to_string(), center.0, top).ok();
+                        }
+                        if text {
+                            let below = if glyphs && color.is_some() { center.1 + size / 6.0 } else { center.1 };
+                            context.set_font(&format!("{}px sans-serif", (size / 4.0).round()));
+                            let probability = i18n::percent(locale, game.probability(x, y), 2);
+                            context.fill_text(&probability, center.0, below).ok();
+                        }
+                    }
+                }
+            });
+        }
+        let (x, y) = selected.get();
+        context.set_stroke_style(&"red".into());
+        context.set_line_width(2.0);
+        context.stroke_rect(
+            y as f64 * size - camera.left,
+            x as f64 * size - camera.top,
+            size.max(2.0),
+            size.max(2.0),
+        );
+    });
+
+    let handle_pointer_down = move |ev: ev::PointerEvent| {
+        let Some(camera) = camera.get_untracked() else {
+            return;
+        };
+        // keeps the moves coming when the pointer leaves the canvas
+        if let Some(canvas) = canvas.get_untracked() {
+            canvas.set_pointer_capture(ev.pointer_id()).ok();
+        }
+        let point = (ev.pointer_id(), ev.offset_x() as f64, ev.offset_y() as f64);
+        pointers.update_value(|pointers| pointers.push(point));
+        if pointers.with_value(Vec::len) == 1 {
+            drag.set_value(Some((ev.client_x() as f64, ev.client_y() as f64, camera)));
+            moved.set_value(false);
+        } else {
+            // a second finger pinches instead, and what it ends with is no tap
+            drag.set_value(None);
+            moved.set_value(true);
+        }
+    };
+    let handle_pointer_move = move |ev: ev::PointerEvent| {
+        let (px, py) = (ev.offset_x() as f64, ev.offset_y() as f64);
+        let before = pointers.with_value(|pointers| pointers.clone());
+        let Some(index) = before.iter().position(|(id, ..)| *id == ev.pointer_id()) else {
+            return;
+        };
+        pointers.update_value(|pointers| pointers[index] = (ev.pointer_id(), px, py));
+        if before.len() == 2 {
+            // zooms by how much farther apart the fingers got, around the point between them
+            let (_, ox, oy) = before[1 - index];
+            let (_, fx, fy) = before[index];
+            let (from, to) = ((fx - ox).hypot(fy - oy), (px - ox).hypot(py - oy));
+            if from > 0.0 && to > 0.0 {
+                set_camera.update(|camera| {
+                    if let Some(camera) = camera {
+                        *camera = camera.zoom(to / from, (px + ox) / 2.0, (py + oy) / 2.0);
+                    }
+                });
+            }
+            return;
+        }
+        let Some((x, y, start)) = drag.get_value() else {
+            return;
+        };
+        let (dx, dy) = (ev.client_x() as f64 - x, ev.client_y() as f64 - y);
+        if dx.abs() > CLICK_SLOP || dy.abs() > CLICK_SLOP {
+            moved.set_value(true);
+        }
+        if moved.get_value() {
+            set_camera.set(Some(Camera {
+                left: start.left - dx,
+                top: start.top - dy,
+                ..start
+            }));
+        }
+    };
+    let handle_pointer_up = move |ev: ev::PointerEvent| {
+        pointers.update_value(|pointers| pointers.retain(|(id, ..)| *id != ev.pointer_id()));
+        if drag.get_value().is_none() {
+            return;
+        }
+        drag.set_value(None);
+        if moved.get_value() {
+            return;
+        }
+        let Some(camera) = camera.get_untracked() else {
+            return;
+        };
+        let (x, y) = camera.cell_at(ev.offset_x() as f64, ev.offset_y() as f64);
+        if game.with_untracked(|game| game.contains(x, y)) {
+            on_select(x, y);
+        }
+    };
+    let handle_pointer_cancel = move |ev: ev::PointerEvent| {
+        pointers.update_value(|pointers| pointers.retain(|(id, ..)| *id != ev.pointer_id()));
+        drag.set_value(None);
+    };
+    let handle_wheel = move |ev: ev::WheelEvent| {
+        ev.prevent_default();
+        let factor = if ev.delta_y() < 0.0 { 1.25 } else { 0.8 };
+        set_camera.update(|camera| {
+            if let Some(camera) = camera {
+                *camera = camera.zoom(factor, ev.offset_x() as f64, ev.offset_y() as f64);
+            }
+        });
+    };
+    // A canvas has no cells for screen readers, so it names the board and the selected cell, which
+    // the arrow keys move
+    let label = move || {
+        let (x, y) = selected.get();
+        let locale = locale.get();
+        game.with(|game| {
+            let color = game.contains(x, y).then(|| game.cell(x, y).color).flatten();
+            tr(
+                locale,
+                "board.canvas",
+                &[
+                    ("rows", i18n::number(locale, game.rows())),
+                    ("columns", i18n::number(locale, game.columns())),
+                    ("cell", cell_label(x, y)),
+                    ("color", i18n::color(locale, color)),
+                ],
+            )
+        })
+    };
+    let zoom_by = move |factor: f64| {
+        if let Some((width, height)) = canvas_size() {
+            set_camera.update(|camera| {
+                if let Some(camera) = camera {
+                    *camera = camera.zoom(factor, width / 2.0, height / 2.0);
+                }
+            });
+        }
+    };
+
+    view! {
+        <div class="canvas-board" dir="ltr">
+            <div node_ref=container class="canvas-board__viewport">
+                <canvas
+                    node_ref=canvas
+                    role="img"
+                    aria-label=label
+                    class="canvas-board__canvas"
+                    on:pointerdown=handle_pointer_down
+                    on:pointermove=handle_pointer_move
+                    on:pointerup=handle_pointer_up
+                    on:pointercancel=handle_pointer_cancel
+                    on:wheel=handle_wheel
+                />
+            </div>
+            <div class="canvas-board__controls">
+                <button on:click=move |_| zoom_by(1.25)>"+"</button>
+                <button on:click=move |_| zoom_by(0.8)>"−"</button>
+                <button on:click=move |_| fit()>{move || t(locale.get(), "button.fit")}</button>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The cell under a point, as fractions of cells, which zooming has to keep
+    fn position(camera: &Camera, px: f64, py: f64) -> (f64, f64) {
+        ((py + camera.top) / camera.cell_size, (px + camera.left) / camera.cell_size)
+    }
+
+    #[test]
+    fn a_fitted_camera_centres_the_board() {
+        // 10 rows of 20 columns in 400x300 pixels: cells of 20 pixels, 50 pixels free above and below
+        let camera = Camera::fit(10, 20, 400.0, 300.0);
+        assert_eq!(camera.cell_size, 20.0);
+        assert_eq!(camera.cell_at(0.0, 50.0), (0, 0));
+        assert_eq!(camera.cell_at(399.0, 50.0), (0, 19));
+        assert_eq!(camera.cell_at(0.0, 249.0), (9, 0));
+        assert_eq!(camera.cell_at(399.0, 249.0), (9, 19));
+        assert_eq!(camera.cell_at(200.0, 150.0), (5, 10));
+        // the free space is off the board
+        assert_eq!(camera.cell_at(200.0, 49.0).0, -1);
+        assert_eq!(camera.cell_at(200.0, 250.0).0, 10);
+    }
+
+    #[test]
+    fn a_board_too_big_to_fit_is_centred_at_the_smallest_cells() {
+        let camera = Camera::fit(1000, 1000, 100.0, 100.0);
+        assert_eq!(camera.cell_size, MIN_CELL_SIZE);
+        assert_eq!(camera.cell_at(50.0, 50.0), (500, 500));
+    }
+
+    #[test]
+    fn zooming_keeps_the_cell_under_the_cursor() {
+        let camera = Camera::fit(30, 40, 800.0, 600.0);
+        for (factor, px, py) in [(1.25, 130.0, 470.0), (0.8, 700.0, 20.0), (1e6, 410.0, 310.0), (1e-6, 5.0, 5.0)] {
+            let zoomed = camera.zoom(factor, px, py);
+            assert!((MIN_CELL_SIZE..=MAX_CELL_SIZE).contains(&zoomed.cell_size));
+            let (before, after) = (position(&camera, px, py), position(&zoomed, px, py));
+            assert!((before.0 - after.0).abs() < 1e-9 && (before.1 - after.1).abs() < 1e-9, "{:?} {:?}", before, after);
+            assert_eq!(zoomed.cell_at(px, py), camera.cell_at(px, py));
+        }
+    }
+}
+
@@ -0,0 +1,20 @@
+use wasm_bindgen::JsCast;
+
+// `navigator.clipboard` is still an unstable API in web-sys, so it is looked up dynamically.
+// Returns false when the browser has no clipboard API (e.g. outside of a secure context).
+pub fn copy(text: &str) -> bool {
+    let navigator = leptos::window().navigator();
+    let Ok(clipboard) = js_sys::Reflect::get(&navigator, &"clipboard".into()) else {
+        return false;
+    };
+    if clipboard.is_undefined() {
+        return false;
+    }
+    let write_text = js_sys::Reflect::get(&clipboard, &"writeText".into())
+        .ok()
+        .and_then(|write_text| write_text.dyn_into::<js_sys::Function>().ok());
+    match write_text {
+        Some(write_text) => write_text.call1(&clipboard, &text.into()).is_ok(),
+        None => false,
+    }
+}
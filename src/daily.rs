@@ -0,0 +1,163 @@
+use crate::game::{Action, Game, GameSettings};
+use crate::i18n::{self, tr, Locale};
+use crate::palette::Appearance;
+use crate::preset::Preset;
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DAILY_KEY: &str = "bust-ghost.daily";
+const PROGRESS_KEY: &str = "bust-ghost.daily.progress";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub won: bool,
+    pub attempts_used: i32,
+    pub busts_used: i32,
+    pub share: String,
+}
+
+// Everyone plays the daily challenge on the normal preset
+pub fn settings() -> GameSettings {
+    Preset::Normal.settings()
+}
+
+// The player's calendar day, as YYYY-MM-DD
+pub fn today() -> String {
+    let now = js_sys::Date::new_0();
+    format!("{:04}-{:02}-{:02}", now.get_full_year(), now.get_month() + 1, now.get_date())
+}
+
+// The server's calendar day in UTC. The ranked challenge changes on the server's day, whatever the
+// player's clock says.
+#[cfg(feature = "ssr")]
+pub fn server_today() -> String {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    date_from_days((now.as_secs() / 86400) as i64)
+}
+
+// The player's calendar day in UTC, the one the server ranks daily challenges on
+pub fn today_utc() -> String {
+    let now = js_sys::Date::new_0();
+    format!("{:04}-{:02}-{:02}", now.get_utc_full_year(), now.get_utc_month() + 1, now.get_utc_date())
+}
+
+// The date `days` days after 1970-01-01, as YYYY-MM-DD
+pub fn date_from_days(days: i64) -> String {
+    // Counted in 400-year eras starting on March 1st, so that the leap day ends the year
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// FNV-1a of the date. Unlike std's hasher it is guaranteed to give the same seed on every
+// platform and release, which is what makes the challenge the same for everyone.
+pub fn seed(date: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in date.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// The day's challenge while it is played. It is saved from the first action on, so reloading the
+// page resumes the game instead of giving the player another go at the same ghost.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyProgress {
+    pub date: String,
+    pub actions: Vec<Action>,
+}
+
+// The actions already played on the challenge of `date`
+pub fn progress(date: &str) -> Vec<Action> {
+    LocalStorage::get::<DailyProgress>(PROGRESS_KEY)
+        .ok()
+        .filter(|progress| progress.date == date)
+        .map(|progress| progress.actions)
+        .unwrap_or_default()
+}
+
+pub fn save_progress(date: &str, actions: &[Action]) {
+    let progress = DailyProgress {
+        date: date.to_string(),
+        actions: actions.to_vec(),
+    };
+    if let Err(err) = LocalStorage::set(PROGRESS_KEY, progress) {
+        gloo::console::error!(format!("Could not save the daily game: {}", err));
+    }
+}
+
+fn results() -> HashMap<String, DailyResult> {
+    LocalStorage::get(DAILY_KEY).unwrap_or_default()
+}
+
+// The result of the day's challenge if it was already played, players only get one attempt
+pub fn result(date: &str) -> Option<DailyResult> {
+    results().remove(date)
+}
+
+pub fn record(date: &str, game: &Game, won: bool, appearance: Appearance, locale: Locale) -> DailyResult {
+    let result = DailyResult {
+        won,
+        attempts_used: game.settings.score - game.score,
+        busts_used: game.settings.busts - game.busts,
+        share: share_text(date, game, won, appearance, locale),
+    };
+    let mut results = results();
+    results.insert(date.to_string(), result.clone());
+    if let Err(err) = LocalStorage::set(DAILY_KEY, results) {
+        gloo::console::error!(format!("Could not save the daily result: {}", err));
+    }
+    LocalStorage::delete(PROGRESS_KEY);
+    result
+}
+
+// The colors sensed on the board and the budget used, without the ghost's position. The board is
+// drawn in the player's palette, or with glyphs when they are on.
+pub fn share_text(date: &str, game: &Game, won: bool, appearance: Appearance, locale: Locale) -> String {
+    let mut text = tr(
+        locale,
+        "daily.share",
+        &[
+            ("date", date.to_string()),
+            ("result", if won { "👻✅" } else { "👻❌" }.to_string()),
+            ("attempts", i18n::number(locale, game.settings.score - game.score)),
+            ("busts", i18n::number(locale, game.settings.busts - game.busts)),
+        ],
+    );
+    text.push('\n');
+    for row in game.cells.chunks(game.columns() as usize) {
+        text.extend(row.iter().map(|cell| appearance.share(cell.color)));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_from_days_counts_from_the_epoch() {
+        assert_eq!(date_from_days(0), "1970-01-01");
+        assert_eq!(date_from_days(31), "1970-02-01");
+        assert_eq!(date_from_days(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn date_from_days_handles_leap_years() {
+        assert_eq!(date_from_days(11016), "2000-02-29");
+        assert_eq!(date_from_days(11017), "2000-03-01");
+        assert_eq!(date_from_days(19782), "2024-02-29");
+        assert_eq!(date_from_days(19783), "2024-03-01");
+        assert_eq!(date_from_days(47541), "2100-03-01");
+    }
+}
@@ -0,0 +1,383 @@
+// A reinforcement learning environment in the style of Gym around `Game`: `reset(seed)` starts a
+// game and `step(action)` plays it with the exact rules of the game.
+use crate::game::{Action, Event, Game, GameSettings, SensorColor, Status, DIRECTIONS};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rewards {
+    pub win: f32,
+    pub loss: f32,
+    // Added for every sensing
+    pub sense: f32,
+    // Added for every bust that misses, on top of `loss` if it was the last one
+    pub miss: f32,
+    // Added for an action that can't be played, which changes nothing else
+    pub invalid: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            win: 1.0,
+            loss: -1.0,
+            sense: 0.0,
+            miss: 0.0,
+            invalid: -0.1,
+        }
+    }
+}
+
+// How each cell of the board is encoded in `Observation::cells`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    // Two values from the last reading of the cell: the color (0 when not sensed, then 1 for green
+    // up to 4 for red) and the direction hint (0 when not sensed, then 1 + its index in `DIRECTIONS`)
+    Compact,
+    // One-hot, from the last reading of the cell: 5 values for the color (not sensed, green,
+    // yellow, orange, red), then 10 for the direction hint (not sensed, then `DIRECTIONS`)
+    #[default]
+    OneHot,
+}
+
+impl Encoding {
+    pub fn features(&self) -> usize {
+        match self {
+            Encoding::Compact => 2,
+            Encoding::OneHot => 1 + SensorColor::ALL.len() + 1 + DIRECTIONS.len(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EnvConfig {
+    pub settings: GameSettings,
+    pub rewards: Rewards,
+    pub encoding: Encoding,
+    // Also observe the probability of the ghost being in each cell given the readings
+    pub posterior: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Observation {
+    pub rows: i32,
+    pub columns: i32,
+    // `Encoding::features` values per cell, row by row
+    pub cells: Vec<f32>,
+    pub attempts_left: i32,
+    pub busts_left: i32,
+    // One probability per cell, row by row
+    pub posterior: Option<Vec<f32>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Info {
+    pub event: Option<Event>,
+    pub status: Option<Status>,
+    pub error: Option<String>,
+    // Revealed once the game is over
+    pub ghost_position: Option<(i32, i32)>,
+    // In a `VecEnv`, the last observation of a game that ended and was replaced by a new one
+    pub final_observation: Option<Observation>,
+}
+
+pub struct Env {
+    pub config: EnvConfig,
+    game: Game,
+    // The color and the direction hint of the last reading of every cell, as indices in
+    // `SensorColor::ALL` and `DIRECTIONS`
+    readings: Vec<Option<(usize, usize)>>,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Result<Env, String> {
+        config.settings.validate()?;
+        let game = Game::from_seed(config.settings.clone(), 0);
+        let readings = vec![None; (game.rows() * game.columns()) as usize];
+        let mut env = Env { config, game, readings };
+        env.reset(0);
+        Ok(env)
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // Every action, in the order of `action_index`: sensing each cell row by row, then busting each
+    pub fn action_count(&self) -> usize {
+        2 * self.readings.len()
+    }
+
+    pub fn action(&self, index: usize) -> Action {
+        let cells = self.readings.len();
+        let cell = (index % cells) as i32;
+        let (x, y) = (cell / self.game.columns(), cell % self.game.columns());
+        if index < cells {
+            Action::Sense { x, y }
+        } else {
+            Action::Bust { x, y }
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::from_seed(self.config.settings.clone(), seed);
+        self.game.place_ghost();
+        self.game.compute_initial_prior_probabilities();
+        self.readings.iter_mut().for_each(|reading| *reading = None);
+        self.observation()
+    }
+
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, Info) {
+        let rewards = &self.config.rewards;
+        let mut info = Info::default();
+        let mut reward = 0.0;
+        match self.game.apply(action) {
+            Ok(event) => {
+                match &event {
+                    Event::Reading { x, y, color, direction } => {
+                        reward += rewards.sense;
+                        let index = (x * self.game.columns() + y) as usize;
+                        let color = SensorColor::ALL.iter().position(|c| c.name() == color);
+                        let direction = DIRECTIONS.iter().position(|d| d == direction);
+                        self.readings[index] = color.zip(direction);
+                    }
+                    Event::Bust { hit: false, .. } => reward += rewards.miss,
+                    Event::Bust { .. } => {}
+                }
+                reward += match self.game.status {
+                    Status::Won => rewards.win,
+                    Status::Lost => rewards.loss,
+                    Status::Playing => 0.0,
+                };
+                info.event = Some(event);
+            }
+            Err(err) => {
+                reward += rewards.invalid;
+                info.error = Some(err);
+            }
+        }
+        let done = self.game.status != Status::Playing;
+        info.status = Some(self.game.status);
+        info.ghost_position = done.then_some(self.game.ghost_position);
+        (self.observation(), reward, done, info)
+    }
+
+    pub fn observation(&self) -> Observation {
+        let encoding = self.config.encoding;
+        let mut cells = Vec::with_capacity(self.readings.len() * encoding.features());
+        for reading in &self.readings {
+            let (color, direction) = (reading.map(|r| r.0), reading.map(|r| r.1));
+            match encoding {
+                Encoding::Compact => {
+                    cells.push(color.map_or(0.0, |c| c as f32 + 1.0));
+                    cells.push(direction.map_or(0.0, |d| d as f32 + 1.0));
+                }
+                Encoding::OneHot => {
+                    let start = cells.len();
+                    cells.resize(start + encoding.features(), 0.0);
+                    cells[start + color.map_or(0, |c| c + 1)] = 1.0;
+                    cells[start + 1 + SensorColor::ALL.len() + direction.map_or(0, |d| d + 1)] = 1.0;
+                }
+            }
+        }
+        Observation {
+            rows: self.game.rows(),
+            columns: self.game.columns(),
+            cells,
+            attempts_left: self.game.score,
+            busts_left: self.game.busts,
+            posterior: self
+                .config
+                .posterior
+                .then(|| self.game.belief.probabilities.iter().map(|probability| *probability as f32).collect()),
+        }
+    }
+}
+
+// A batch of environments stepped together. A game that ends is reset right away with the next
+// seed, its last observation is in `Info::final_observation`.
+pub struct VecEnv {
+    pub envs: Vec<Env>,
+    seeds: ChaCha8Rng,
+}
+
+impl VecEnv {
+    pub fn new(config: EnvConfig, count: usize, seed: u64) -> Result<VecEnv, String> {
+        Ok(VecEnv {
+            envs: (0..count).map(|_| Env::new(config.clone())).collect::<Result<_, _>>()?,
+            seeds: ChaCha8Rng::seed_from_u64(seed),
+        })
+    }
+
+    pub fn reset(&mut self) -> Vec<Observation> {
+        self.envs.iter_mut().map(|env| env.reset(self.seeds.gen())).collect()
+    }
+
+    // One action per environment
+    pub fn step(&mut self, actions: &[Action]) -> Vec<(Observation, f32, bool, Info)> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per environment");
+        self.envs
+            .iter_mut()
+            .zip(actions)
+            .map(|(env, action)| {
+                let (observation, reward, done, mut info) = env.step(*action);
+                if !done {
+                    return (observation, reward, done, info);
+                }
+                info.final_observation = Some(observation);
+                (env.reset(self.seeds.gen()), reward, done, info)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::Preset;
+
+    fn config(encoding: Encoding, busts: i32) -> EnvConfig {
+        EnvConfig {
+            settings: GameSettings { busts, ..Preset::Normal.settings() },
+            rewards: Rewards { sense: -0.01, miss: -0.2, ..Rewards::default() },
+            encoding,
+            posterior: true,
+        }
+    }
+
+    // A cell the ghost isn't on
+    fn empty_cell(env: &Env) -> (i32, i32) {
+        let (x, y) = env.game().ghost_position;
+        ((x + 1) % env.game().rows(), y)
+    }
+
+    fn indices(reading: &Info) -> (usize, usize) {
+        match &reading.event {
+            Some(Event::Reading { color, direction, .. }) => (
+                SensorColor::ALL.iter().position(|c| c.name() == color).unwrap(),
+                DIRECTIONS.iter().position(|d| d == direction).unwrap(),
+            ),
+            event => panic!("not a reading: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn new_rejects_invalid_settings() {
+        let mut config = config(Encoding::OneHot, 2);
+        config.settings.width = 0;
+        assert!(Env::new(config.clone()).is_err());
+        assert!(VecEnv::new(config, 2, 1).is_err());
+    }
+
+    #[test]
+    fn actions_sense_then_bust_every_cell_row_by_row() {
+        let env = Env::new(config(Encoding::OneHot, 2)).unwrap();
+        let (rows, columns) = (env.game().rows(), env.game().columns());
+        let cells = (rows * columns) as usize;
+        assert_eq!(env.action_count(), 2 * cells);
+        assert_eq!(env.action(0), Action::Sense { x: 0, y: 0 });
+        assert_eq!(env.action(columns as usize + 2), Action::Sense { x: 1, y: 2 });
+        assert_eq!(env.action(2 * cells - 1), Action::Bust { x: rows - 1, y: columns - 1 });
+    }
+
+    #[test]
+    fn reset_starts_a_fresh_unsensed_game() {
+        let mut env = Env::new(config(Encoding::OneHot, 2)).unwrap();
+        env.step(Action::Sense { x: 0, y: 0 });
+        let observation = env.reset(9);
+        let settings = &env.config.settings;
+        assert_eq!((observation.attempts_left, observation.busts_left), (settings.score, settings.busts));
+        assert_eq!(observation.cells.len(), (settings.width * settings.height) as usize * Encoding::OneHot.features());
+        for cell in observation.cells.chunks(Encoding::OneHot.features()) {
+            assert_eq!(cell[0], 1.0);
+            assert_eq!(cell[1 + SensorColor::ALL.len()], 1.0);
+            assert_eq!(cell.iter().sum::<f32>(), 2.0);
+        }
+        let posterior = observation.posterior.unwrap();
+        assert!((posterior.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sensing_costs_its_reward_and_encodes_the_last_reading() {
+        let mut env = Env::new(config(Encoding::Compact, 2)).unwrap();
+        env.reset(4);
+        let cell = (2 * env.game().columns() + 3) as usize * Encoding::Compact.features();
+        let (observation, reward, done, info) = env.step(Action::Sense { x: 2, y: 3 });
+        assert_eq!((reward, done), (-0.01, false));
+        let (color, direction) = indices(&info);
+        assert_eq!(observation.cells[cell..cell + 2], [color as f32 + 1.0, direction as f32 + 1.0]);
+        assert!(observation.cells[..cell].iter().all(|value| *value == 0.0));
+        assert_eq!(observation.attempts_left, env.config.settings.score - 1);
+
+        // a second reading of the cell replaces both the color and the direction of the first
+        for _ in 0..5 {
+            let (observation, _, _, info) = env.step(Action::Sense { x: 2, y: 3 });
+            let (color, direction) = indices(&info);
+            assert_eq!(observation.cells[cell..cell + 2], [color as f32 + 1.0, direction as f32 + 1.0]);
+        }
+    }
+
+    #[test]
+    fn one_hot_marks_the_color_and_direction_of_a_reading() {
+        let mut env = Env::new(config(Encoding::OneHot, 2)).unwrap();
+        let (observation, _, _, info) = env.step(Action::Sense { x: 1, y: 1 });
+        let (color, direction) = indices(&info);
+        let features = Encoding::OneHot.features();
+        let start = (observation.columns + 1) as usize * features;
+        let mut expected = vec![0.0; features];
+        expected[1 + color] = 1.0;
+        expected[1 + SensorColor::ALL.len() + 1 + direction] = 1.0;
+        assert_eq!(observation.cells[start..start + features], expected[..]);
+    }
+
+    #[test]
+    fn busting_the_ghost_wins_and_reveals_it() {
+        let mut env = Env::new(config(Encoding::OneHot, 2)).unwrap();
+        let (x, y) = env.game().ghost_position;
+        let (_, reward, done, info) = env.step(Action::Bust { x, y });
+        assert_eq!((reward, done), (1.0, true));
+        assert_eq!(info.status, Some(Status::Won));
+        assert_eq!(info.ghost_position, Some((x, y)));
+    }
+
+    #[test]
+    fn missing_the_last_bust_loses() {
+        let mut env = Env::new(config(Encoding::OneHot, 2)).unwrap();
+        let (x, y) = empty_cell(&env);
+        let (observation, reward, done, info) = env.step(Action::Bust { x, y });
+        assert_eq!((reward, done, info.ghost_position), (-0.2, false, None));
+        assert_eq!(observation.posterior.unwrap()[(x * observation.columns + y) as usize], 0.0);
+        let (_, reward, done, info) = env.step(Action::Bust { x, y });
+        assert_eq!((reward, done), (-0.2 + -1.0, true));
+        assert_eq!(info.status, Some(Status::Lost));
+    }
+
+    #[test]
+    fn an_action_that_cant_be_played_changes_nothing() {
+        let mut env = Env::new(config(Encoding::OneHot, 2)).unwrap();
+        let before = env.observation();
+        let (observation, reward, done, info) = env.step(Action::Sense { x: -1, y: 0 });
+        assert_eq!((reward, done), (-0.1, false));
+        assert!(info.error.is_some() && info.event.is_none());
+        assert_eq!((observation.cells, observation.attempts_left), (before.cells, before.attempts_left));
+    }
+
+    #[test]
+    fn a_vec_env_resets_the_games_that_end() {
+        let mut envs = VecEnv::new(config(Encoding::OneHot, 1), 2, 5).unwrap();
+        envs.reset();
+        let (x, y) = empty_cell(&envs.envs[0]);
+        let results = envs.step(&[Action::Bust { x, y }, Action::Sense { x: 0, y: 0 }]);
+
+        let (observation, reward, done, info) = &results[0];
+        assert_eq!((*reward, *done), (-0.2 + -1.0, true));
+        assert_eq!(info.final_observation.as_ref().map(|last| last.busts_left), Some(0));
+        assert_eq!(observation.busts_left, 1);
+        assert_eq!(envs.envs[0].game().status, Status::Playing);
+
+        let (observation, _, done, info) = &results[1];
+        assert!(!done && info.final_observation.is_none());
+        assert_eq!(observation.attempts_left, envs.envs[1].config.settings.score - 1);
+    }
+}
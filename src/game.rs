@@ -1,28 +1,66 @@
-use gloo::console::log;
+use crate::i18n::{self, tr, Locale};
+use crate::inference::{ExactGrid, InferenceEngine};
+use crate::preset::Preset;
 use rand::distributions::Distribution;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
+// What is known about a cell besides the probability of the ghost being there, which is in
+// `Game::belief`
 #[derive(Clone, Debug)]
 pub struct Cell {
     pub x: i32,
     pub y: i32,
-    pub color: String,
-    pub probability: f32,
-    pub visited: bool,
+    // The color of the first reading, `None` until the cell is sensed
+    pub color: Option<SensorColor>,
+    // How many times the cell was sensed, each sensing of a cell gets its own draws
+    pub senses: i32,
 }
 
 impl Cell {
-    pub fn new(x: i32, y: i32, color: String, probability: f32) -> Cell {
+    pub fn new(x: i32, y: i32) -> Cell {
         Cell {
             x,
             y,
-            color,
-            probability,
-            visited: false,
+            color: None,
+            senses: 0,
         }
     }
+
+    pub fn visited(&self) -> bool {
+        self.color.is_some()
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorColor {
+    Green,
+    Yellow,
+    Orange,
+    Red,
+}
+
+impl SensorColor {
+    // In the order of `ConditionalProbabilities::colors`
+    pub const ALL: [SensorColor; 4] = [SensorColor::Green, SensorColor::Yellow, SensorColor::Orange, SensorColor::Red];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensorColor::Green => "green",
+            SensorColor::Yellow => "yellow",
+            SensorColor::Orange => "orange",
+            SensorColor::Red => "red",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SensorColor> {
+        SensorColor::ALL.into_iter().find(|color| color.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ConditionalProbabilities {
     distance: i32,
     green: f32,
@@ -45,138 +83,473 @@ impl ConditionalProbabilities {
             yellow,
             orange,
             red,
-            // yellow: yellow + green,
-            // orange: orange + yellow + green,
-            // red: red + orange + yellow + green,
+        }
+    }
+
+    // The distance from the ghost this row is for
+    pub fn distance(&self) -> i32 {
+        self.distance
+    }
+
+    // The probabilities of green, yellow, orange and red, in that order
+    pub fn colors(&self) -> [f32; 4] {
+        [self.green, self.yellow, self.orange, self.red]
+    }
+
+    // The probability of sensing `color` with this row. The sensor draws from a row as weights, so
+    // a row that doesn't add up to 1 is divided by its sum.
+    pub fn likelihood(&self, color: SensorColor) -> f64 {
+        let colors = self.colors();
+        colors[color as usize] as f64 / colors.iter().map(|p| *p as f64).sum::<f64>()
+    }
+}
+
+// The row of the sensor table drawn from with the ghost `distance` cells away: the row of that
+// index, or the last one for anything farther than the table goes
+pub fn sensor_row(conditional_probabilities: &[ConditionalProbabilities], distance: i32) -> usize {
+    (distance.max(0) as usize).min(conditional_probabilities.len() - 1)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Action {
+    Sense { x: i32, y: i32 },
+    Bust { x: i32, y: i32 },
+}
+
+impl Action {
+    pub fn cell(&self) -> (i32, i32) {
+        match *self {
+            Action::Sense { x, y } | Action::Bust { x, y } => (x, y),
+        }
+    }
+}
+
+// What an action revealed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    Reading { x: i32, y: i32, color: String, direction: String },
+    Bust { x: i32, y: i32, hit: bool },
+}
+
+impl Event {
+    pub fn cell(&self) -> (i32, i32) {
+        match *self {
+            Event::Reading { x, y, .. } | Event::Bust { x, y, .. } => (x, y),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Playing,
+    Won,
+    Lost,
+}
+
+// Every answer `relative_direction` can give
+pub const DIRECTIONS: [&str; 9] = ["N", "S", "E", "W", "NE", "NW", "SE", "SW", "BINGO!"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    N,
+    S,
+    E,
+    W,
+    NE,
+    NW,
+    SE,
+    SW,
+    Bingo,
+}
+
+impl Direction {
+    // In the order of `DIRECTIONS`
+    pub const ALL: [Direction; 9] = [
+        Direction::N,
+        Direction::S,
+        Direction::E,
+        Direction::W,
+        Direction::NE,
+        Direction::NW,
+        Direction::SE,
+        Direction::SW,
+        Direction::Bingo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        DIRECTIONS[*self as usize]
+    }
+
+    pub fn from_name(name: &str) -> Option<Direction> {
+        DIRECTIONS.iter().position(|d| *d == name).map(|i| Direction::ALL[i])
+    }
+
+    // The direction of the ghost on the cell (ghost_x, ghost_y) seen from the cell (x, y)
+    pub fn between(x: i32, y: i32, ghost_x: i32, ghost_y: i32) -> Direction {
+        Direction::from_sides((ghost_x - x).signum(), (ghost_y - y).signum())
+    }
+
+    // `row` is -1 when the ghost is on a row above, 0 on the same row and 1 below, `column` is -1
+    // on a column to the left, 0 on the same column and 1 to the right
+    pub fn from_sides(row: i32, column: i32) -> Direction {
+        match (row, column) {
+            (-1, -1) => Direction::NW,
+            (-1, 1) => Direction::NE,
+            (-1, _) => Direction::N,
+            (1, -1) => Direction::SW,
+            (1, 1) => Direction::SE,
+            (1, _) => Direction::S,
+            (_, -1) => Direction::W,
+            (_, 1) => Direction::E,
+            _ => Direction::Bingo,
         }
     }
 }
 
 pub fn relative_direction(x: i32, y: i32, ghost_x: i32, ghost_y: i32) -> String {
-    if ghost_x < x {
-        if ghost_y < y {
-            "NW".to_string()
-        } else if ghost_y > y {
-            "NE".to_string()
-        } else {
-            "N".to_string()
+    Direction::between(x, y, ghost_x, ghost_y).name().to_string()
+}
+
+// What a reading did to the probability of one cell: the prior is multiplied by the probability of
+// the color in the sensor row for the cell's distance and by the probability of the direction, then
+// divided by the sum of these products over the board
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellFactors {
+    pub prior: f64,
+    // The row of `conditional_probabilities`, the last one for anything farther than the table goes
+    pub row: usize,
+    pub color: f64,
+    pub direction: f64,
+    pub posterior: f64,
+}
+
+impl CellFactors {
+    pub fn unnormalised(&self) -> f64 {
+        self.prior * (self.color * self.direction)
+    }
+}
+
+// The factors of every cell for one reading, in the order of `Game::cells`
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeliefUpdate {
+    pub x: i32,
+    pub y: i32,
+    pub color: SensorColor,
+    pub direction: Option<Direction>,
+    pub cells: Vec<CellFactors>,
+    // What every unnormalised probability is divided by
+    pub normalisation: f64,
+}
+
+pub const MIN_BOARD_SIZE: i32 = 3;
+pub const MAX_BOARD_SIZE: i32 = 1000;
+
+// Why `GameSettings::validate` turned the settings down. The server and the protocol report it in
+// English, the settings dialog in the player's language.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingsError {
+    BoardTooSmall,
+    BoardTooLarge,
+    NoAttempts,
+    NoBusts,
+    DirectionReliability,
+    NoSensorRows,
+    // The distance of a row that can't be drawn from
+    SensorRow(i32),
+}
+
+impl SettingsError {
+    pub fn message(&self, locale: Locale) -> String {
+        match self {
+            SettingsError::BoardTooSmall => {
+                tr(locale, "settings.error.too_small", &[("size", i18n::number(locale, MIN_BOARD_SIZE))])
+            }
+            SettingsError::BoardTooLarge => {
+                tr(locale, "settings.error.too_large", &[("size", i18n::number(locale, MAX_BOARD_SIZE))])
+            }
+            SettingsError::NoAttempts => tr(locale, "settings.error.no_attempts", &[]),
+            SettingsError::NoBusts => tr(locale, "settings.error.no_busts", &[]),
+            SettingsError::DirectionReliability => tr(locale, "settings.error.direction_reliability", &[]),
+            SettingsError::NoSensorRows => tr(locale, "settings.error.no_sensor_rows", &[]),
+            SettingsError::SensorRow(distance) => {
+                tr(locale, "settings.error.sensor_row", &[("distance", i18n::number(locale, distance))])
+            }
         }
-    } else if ghost_x > x {
-        if ghost_y < y {
-            "SW".to_string()
-        } else if ghost_y > y {
-            "SE".to_string()
-        } else {
-            "S".to_string()
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message(Locale::En))
+    }
+}
+
+impl From<SettingsError> for String {
+    fn from(err: SettingsError) -> String {
+        err.to_string()
+    }
+}
+
+// What the player picks in the new game dialog, or what a `Preset` bundles. `width` is the number
+// of columns (the `y` axis of the grid) and `height` the number of rows (the `x` axis).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameSettings {
+    pub width: i32,
+    pub height: i32,
+    pub score: i32,
+    pub busts: i32,
+    pub conditional_probabilities: Vec<ConditionalProbabilities>,
+    // Probability that the direction hint points at the ghost instead of a random other direction
+    pub direction_reliability: f32,
+    pub allow_peep: bool,
+    // `None` once the player changed anything the preset defines
+    pub preset: Option<Preset>,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Preset::Normal.settings()
+    }
+}
+
+impl GameSettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.width < MIN_BOARD_SIZE || self.height < MIN_BOARD_SIZE {
+            return Err(SettingsError::BoardTooSmall);
         }
-    } else {
-        if ghost_y < y {
-            "W".to_string()
-        } else if ghost_y > y {
-            "E".to_string()
-        } else {
-            "BINGO!".to_string()
+        if self.width > MAX_BOARD_SIZE || self.height > MAX_BOARD_SIZE {
+            return Err(SettingsError::BoardTooLarge);
+        }
+        if self.score < 1 {
+            return Err(SettingsError::NoAttempts);
         }
+        if self.busts < 1 {
+            return Err(SettingsError::NoBusts);
+        }
+        if !(0.0..=1.0).contains(&self.direction_reliability) {
+            return Err(SettingsError::DirectionReliability);
+        }
+        if self.conditional_probabilities.is_empty() {
+            return Err(SettingsError::NoSensorRows);
+        }
+        // Every row is drawn from as weights, see `Game::distance_sense`
+        for row in &self.conditional_probabilities {
+            let colors = row.colors();
+            if colors.iter().any(|p| !p.is_finite() || *p < 0.0) || colors.iter().sum::<f32>() <= 0.0 {
+                return Err(SettingsError::SensorRow(row.distance));
+            }
+        }
+        Ok(())
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Game {
-    pub grid: Vec<Vec<Cell>>,
+    // The cells row by row, see `index`
+    pub cells: Vec<Cell>,
+    // The probability of the ghost being in each cell, in the order of `cells`
+    pub belief: ExactGrid,
     pub ghost_position: (i32, i32),
     pub score: i32,
     pub busts: i32,
     pub conditional_probabilities: Vec<ConditionalProbabilities>,
+    pub settings: GameSettings,
+    // The ghost position and every sensor draw follow from the seed, so a game can be replayed
+    pub seed: u64,
+    pub status: Status,
+    // When false, readings only color the cells and `belief` is left to whoever keeps it up to
+    // date, like the engine worker
+    pub update_belief: bool,
 }
 
-// implement the clone trait for the Game struct
+// The draws for the n-th sensing of a cell only depend on the seed, the cell and n, so two players
+// sensing the same cells of the same seed get the same readings whatever order they sense them in.
+// Streams start at 1 so they never repeat the draws that placed the ghost.
+fn sensor_rng(seed: u64, x: i32, y: i32, n: i32) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(1 + ((x as u64) << 40 | (y as u64) << 20 | n as u64));
+    rng
+}
 
 impl Game {
-    pub fn new(w: i32, h: i32) -> Game {
-        let mut grid = vec![];
-        for x in 0..w {
-            let mut row = vec![];
-            for y in 0..h {
-                row.push(Cell::new(x, y, "white".to_string(), 0.0));
-            }
-            grid.push(row);
-        }
-        let conditional_probabilities = vec![
-            ConditionalProbabilities::new(0, 0.05, 0.05, 0.10, 0.80),
-            ConditionalProbabilities::new(1, 0.05, 0.10, 0.75, 0.10),
-            ConditionalProbabilities::new(2, 0.05, 0.10, 0.75, 0.10),
-            ConditionalProbabilities::new(3, 0.10, 0.70, 0.15, 0.05),
-            ConditionalProbabilities::new(4, 0.10, 0.70, 0.15, 0.05),
-            ConditionalProbabilities::new(5, 0.70, 0.10, 0.10, 0.05),
-        ];
+    pub fn new(rows: i32, columns: i32) -> Game {
+        Game::from_settings(GameSettings {
+            width: columns,
+            height: rows,
+            ..GameSettings::default()
+        })
+    }
+
+    pub fn from_settings(settings: GameSettings) -> Game {
+        Game::from_seed(settings, rand::random())
+    }
+
+    pub fn from_seed(settings: GameSettings, seed: u64) -> Game {
+        let cells = (0..settings.height)
+            .flat_map(|x| (0..settings.width).map(move |y| Cell::new(x, y)))
+            .collect::<Vec<_>>();
+        let conditional_probabilities = settings.conditional_probabilities.clone();
         Game {
-            grid,
+            belief: ExactGrid::new(&settings),
+            cells,
             ghost_position: (0, 0),
-            score: 30,
-            busts: 2,
+            score: settings.score,
+            busts: settings.busts,
             conditional_probabilities,
+            settings,
+            seed,
+            status: Status::Playing,
+            update_belief: true,
         }
     }
 
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    pub fn rows(&self) -> i32 {
+        self.settings.height
+    }
+
+    pub fn columns(&self) -> i32 {
+        self.settings.width
+    }
+
+    // Where the cell (x, y) is in `cells` and `belief`
+    pub fn index(&self, x: i32, y: i32) -> usize {
+        (x * self.columns() + y) as usize
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> &Cell {
+        &self.cells[self.index(x, y)]
+    }
+
+    pub fn probability(&self, x: i32, y: i32) -> f64 {
+        self.belief.probabilities[self.index(x, y)]
+    }
+
     pub fn reset(&mut self) {
-        for x in 0..self.grid.len() {
-            for y in 0..self.grid[0].len() {
-                self.grid[x as usize][y as usize].color = "white".to_string();
-                self.grid[x as usize][y as usize].probability = 0.0;
-                self.grid[x as usize][y as usize].visited = false;
-            }
+        for cell in &mut self.cells {
+            cell.color = None;
+            cell.senses = 0;
         }
-        self.score = 30;
-        self.busts = 2;
+        self.score = self.settings.score;
+        self.busts = self.settings.busts;
+        self.status = Status::Playing;
+        self.reseed(rand::random());
         self.place_ghost();
         self.compute_initial_prior_probabilities();
     }
 
     pub fn place_ghost(&mut self) {
-        let x = (rand::random::<i32>()).abs() % self.grid.len() as i32;
-        let y = (rand::random::<i32>()).abs() % self.grid[0].len() as i32;
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let x = rng.gen_range(0..self.rows());
+        let y = rng.gen_range(0..self.columns());
         self.ghost_position = (x, y);
     }
 
     pub fn compute_initial_prior_probabilities(&mut self) {
-        for x in 0..self.grid.len() {
-            for y in 0..self.grid[0].len() {
-                self.grid[x as usize][y as usize].probability =
-                    1.0 / (self.grid.len() * self.grid[0].len()) as f32;
-            }
-        }
+        self.belief.reset();
     }
 
-    pub fn distance_sense(&mut self, x: i32, y: i32) -> (String, String) {
-        self.score -= 1;
+    // Fails, without costing an attempt, when the sensor row can't be drawn from: settings that
+    // didn't go through `GameSettings::validate` may have rows of zeros or negative weights
+    pub fn distance_sense(&mut self, x: i32, y: i32) -> Result<(String, String), String> {
         // Distance needs to be between 0 and 5
         let distance = (self.ghost_position.0 - x).abs() + (self.ghost_position.1 - y).abs();
 
-        let distance = if distance > 5 { 5 } else { distance };
-
-        // let between = rand::distributions::Uniform::from(0.0..1.0);
-        // let mut rng = rand::thread_rng();
-        // let random_number = between.sample_iter(&mut rng).next().unwrap();
         let choices = ["green", "yellow", "orange", "red"];
-        let weights = [
-            self.conditional_probabilities[distance as usize].green * 100.0,
-            self.conditional_probabilities[distance as usize].yellow * 100.0,
-            self.conditional_probabilities[distance as usize].orange * 100.0,
-            self.conditional_probabilities[distance as usize].red * 100.0,
-        ];
-        log!(&format!("Weights: {:?}", weights));
-        let dist = rand::distributions::WeightedIndex::new(&weights).unwrap();
-        let mut rng = rand::thread_rng();
+        if self.conditional_probabilities.is_empty() {
+            return Err("The sensor has no distance rows".to_string());
+        }
+        let row = &self.conditional_probabilities[sensor_row(&self.conditional_probabilities, distance)];
+        let weights = [row.green * 100.0, row.yellow * 100.0, row.orange * 100.0, row.red * 100.0];
+        let dist = rand::distributions::WeightedIndex::new(&weights)
+            .map_err(|err| format!("The sensor row for distance {} can't be drawn from: {}", row.distance, err))?;
+        self.score -= 1;
+        let index = self.index(x, y);
+        let cell = &mut self.cells[index];
+        let mut rng = sensor_rng(self.seed, x, y, cell.senses);
+        cell.senses += 1;
         let random_color = choices[dist.sample(&mut rng)];
-        log!("Random number: {}, distance: {}", random_color, distance);
-        log!(&format!("Ghost position: {}, {}", self.ghost_position.0, self.ghost_position.1));
 
         // Get direction of ghost relative to the cell (NE, NW, SE, SW)
-        
-        let direction = relative_direction(x, y, self.ghost_position.0, self.ghost_position.1);
+        let mut direction = Direction::between(x, y, self.ghost_position.0, self.ghost_position.1);
+        if rng.gen::<f32>() >= self.settings.direction_reliability {
+            let others = Direction::ALL.iter().filter(|d| **d != direction).collect::<Vec<_>>();
+            direction = *others[rng.gen_range(0..others.len())];
+        }
+
+        Ok((random_color.to_string(), direction.name().to_string()))
+    }
 
-        (random_color.to_string(), direction.to_string())
+    // Senses the cell and, the first time it is sensed, records its color and updates the
+    // probabilities. Sensing a cell again still costs an attempt and gives a new direction hint.
+    pub fn sense(&mut self, x: i32, y: i32) -> Result<(String, String), String> {
+        let (color, direction) = self.distance_sense(x, y)?;
+        self.observe(x, y, &color, &direction);
+        Ok((color, direction))
+    }
+
+    // Records a reading of the cell (x, y), wherever it was drawn. A client playing against the
+    // server only knows the readings, not the ghost, and keeps its probabilities with this.
+    pub fn observe(&mut self, x: i32, y: i32, color: &str, direction: &str) {
+        let index = self.index(x, y);
+        let cell = &mut self.cells[index];
+        if !cell.visited() {
+            // Anything that isn't green, yellow or orange reads as red, like the posterior update does
+            cell.color = Some(SensorColor::from_name(color).unwrap_or(SensorColor::Red));
+            if self.update_belief {
+                self.update_posterior_ghost_location_probabilities(color.to_string(), x, y, direction.to_string());
+            }
+        }
+    }
 
+    // Records a missed bust of the cell (x, y): the ghost can't be there. `apply` already does this
+    // when the game keeps its own probabilities.
+    pub fn rule_out(&mut self, x: i32, y: i32) {
+        self.belief.rule_out(x, y);
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (0..self.rows()).contains(&x) && (0..self.columns()).contains(&y)
+    }
+
+    // Plays an action with the full rules of the game: you lose when you run out of attempts or
+    // busts, and nothing can be played once the game is over.
+    pub fn apply(&mut self, action: Action) -> Result<Event, String> {
+        if self.status != Status::Playing {
+            return Err("The game is over".to_string());
+        }
+        let (x, y) = action.cell();
+        if !self.contains(x, y) {
+            return Err(format!("({}, {}) is not on the board", x, y));
+        }
+        let event = match action {
+            Action::Sense { x, y } => {
+                let (color, direction) = self.sense(x, y)?;
+                if self.score == 0 {
+                    self.status = Status::Lost;
+                }
+                Event::Reading { x, y, color, direction }
+            }
+            Action::Bust { x, y } => {
+                let result = self.bust_ghost(x, y);
+                self.status = match result {
+                    1 => Status::Won,
+                    0 => Status::Lost,
+                    _ => Status::Playing,
+                };
+                if result != 1 && self.update_belief {
+                    self.rule_out(x, y);
+                }
+                Event::Bust { x, y, hit: result == 1 }
+            }
+        };
+        Ok(event)
     }
 
     pub fn bust_ghost(&mut self, x: i32, y: i32) -> i8 {
@@ -190,50 +563,70 @@ impl Game {
         }
     }
 
+    // What a reading of `color` and `direction` on the cell (x, y) would do to every cell, without
+    // doing it. The posteriors are the ones `observe` computes.
+    pub fn explain_reading(&self, x: i32, y: i32, color: &str, direction: &str) -> BeliefUpdate {
+        let color = SensorColor::from_name(color).unwrap_or(SensorColor::Red);
+        self.belief.explain(x, y, color, Direction::from_name(direction))
+    }
+
     pub fn update_posterior_ghost_location_probabilities(&mut self, color: String, x: i32, y: i32, g_direction: String) {
         // Update the probabilities of the ghost being in each cell based on the color sensed in the cell (x, y)
         // and the other sensed colors in the grid
+        self.belief.observe(x, y, &color, &g_direction);
+    }
+}
 
-        let mut sum = 0.0;
-        for i in 0..self.grid.len() {
-            for j in 0..self.grid[0].len() {
-                // Get the (i, j) cell's distance from the ghost position based on its distance from the (x, y) cell
-                // and the distance between the (x, y) cell and the ghost position (given by the
-                // color)
-
-                let dist = (x - i as i32).abs() + (y - j as i32).abs();
-
-                let index = match self
-                    .conditional_probabilities
-                    .iter()
-                    .position(|p| p.distance == dist)
-                {
-                    Some(i) => i,
-                    None => 5,
-                };
-                let prior = self.grid[i as usize][j as usize].probability;
-                let likelihood = if color == "green" {
-                    self.conditional_probabilities[index].green
-                } else if color == "yellow" {
-                    self.conditional_probabilities[index].yellow
-                } else if color == "orange" {
-                    self.conditional_probabilities[index].orange
-                } else {
-                    self.conditional_probabilities[index].red
-                };
-                let direction = relative_direction(x, y, i as i32, j as i32);
-                let directed_likelihood = match direction.as_str() == g_direction.as_str() {
-                    true => 0.75,
-                    false => 0.25,
-                };
-                self.grid[i as usize][j as usize].probability = prior * likelihood * directed_likelihood;
-                sum += self.grid[i as usize][j as usize].probability;
-            }
-        }
-        for i in 0..self.grid.len() {
-            for j in 0..self.grid[0].len() {
-                self.grid[i as usize][j as usize].probability /= sum;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_row(row: ConditionalProbabilities) -> GameSettings {
+        let mut settings = Preset::Normal.settings();
+        settings.conditional_probabilities[0] = row;
+        settings
+    }
+
+    #[test]
+    fn validate_rejects_sensor_rows_that_cant_be_drawn_from() {
+        assert!(Preset::Normal.settings().validate().is_ok());
+        for row in [
+            ConditionalProbabilities::new(0, 0.0, 0.0, 0.0, 0.0),
+            ConditionalProbabilities::new(0, -0.5, 0.5, 0.5, 0.5),
+            ConditionalProbabilities::new(0, f32::NAN, 0.5, 0.5, 0.5),
+            ConditionalProbabilities::new(0, f32::INFINITY, 0.5, 0.5, 0.5),
+        ] {
+            assert_eq!(with_row(row.clone()).validate(), Err(SettingsError::SensorRow(0)), "{:?}", row);
         }
     }
+
+    #[test]
+    fn sensing_with_a_bad_row_fails_without_costing_an_attempt() {
+        let mut game = Game::from_seed(with_row(ConditionalProbabilities::new(0, 0.0, 0.0, 0.0, 0.0)), 1);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        let (x, y) = game.ghost_position;
+        assert!(game.apply(Action::Sense { x, y }).is_err());
+        assert_eq!(game.score, game.settings.score);
+        assert_eq!(game.status, Status::Playing);
+    }
+
+    #[test]
+    fn sensor_rows_are_divided_by_their_sum() {
+        let row = ConditionalProbabilities::new(5, 0.7, 0.1, 0.1, 0.05);
+        let total = SensorColor::ALL.iter().map(|color| row.likelihood(*color)).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-12);
+        assert!((row.likelihood(SensorColor::Green) - 0.7 / 0.95).abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_missed_bust_rules_the_cell_out() {
+        let mut game = Game::from_seed(Preset::Normal.settings(), 5);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        let (x, y) = if game.ghost_position == (0, 0) { (0, 1) } else { (0, 0) };
+        assert_eq!(game.apply(Action::Bust { x, y }), Ok(Event::Bust { x, y, hit: false }));
+        assert_eq!(game.probability(x, y), 0.0);
+        assert!((game.belief.probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
 }
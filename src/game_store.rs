@@ -0,0 +1,228 @@
+use crate::game::{Action, Event, Game, Status};
+use actix_web::HttpRequest;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+// Games nobody played for this long are dropped
+const EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+// How many games the server keeps, and how many unfinished ones a client may have. Games take
+// memory in proportion to their board, so without a cap anyone could fill the server's memory.
+pub const MAX_GAMES: usize = 1000;
+pub const MAX_GAMES_PER_CLIENT: usize = 20;
+
+// What the leaderboard may rank a game as
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ranking {
+    // The client picked the seed, so it could know where the ghost is
+    Unranked,
+    // A game on a seed the server drew
+    Ranked,
+    // The daily challenge the server created on that date, on a seed only the server knows
+    Daily(String),
+}
+
+// Games played against the server. They never leave it, clients only get to see what their
+// actions reveal.
+struct Entry {
+    game: Game,
+    // What every action played so far revealed, oldest first
+    history: Vec<Event>,
+    touched: Instant,
+    ranking: Ranking,
+    // Whether the game went to the leaderboard already
+    submitted: bool,
+    // The address of the client that started the game, see `client`
+    client: String,
+}
+
+// Playing never panics with the lock held, but if anything ever did, the other games would still
+// be fine to play, so a poisoned lock is taken over rather than failing every game after it
+fn games() -> MutexGuard<'static, HashMap<String, Entry>> {
+    static GAMES: OnceLock<Mutex<HashMap<String, Entry>>> = OnceLock::new();
+    GAMES.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// The client a game belongs to, for `MAX_GAMES_PER_CLIENT`. The address the connection comes from,
+// as headers that name another one are up to the client.
+pub fn client(request: &HttpRequest) -> String {
+    request.peer_addr().map(|address| address.ip().to_string()).unwrap_or_default()
+}
+
+// Makes room for one more game of `client`, or says why there is none. When the server is full,
+// finished games, which are only kept for their history, are dropped oldest first.
+fn make_room(
+    games: &mut HashMap<String, Entry>,
+    client: &str,
+    max_games: usize,
+    per_client: usize,
+) -> Result<(), String> {
+    games.retain(|_, entry| entry.touched.elapsed() < EXPIRY);
+    let playing = |entry: &Entry| entry.game.status == Status::Playing;
+    if games.values().filter(|entry| entry.client == client && playing(entry)).count() >= per_client {
+        return Err(format!("You can't have more than {} unfinished games", per_client));
+    }
+    if games.len() >= max_games {
+        let mut finished = games
+            .iter()
+            .filter(|(_, entry)| !playing(entry))
+            .map(|(id, entry)| (entry.touched, id.clone()))
+            .collect::<Vec<_>>();
+        finished.sort();
+        for (_, id) in finished.into_iter().take(games.len() + 1 - max_games) {
+            games.remove(&id);
+        }
+    }
+    if games.len() >= max_games {
+        return Err("The server is playing too many games, try again later".to_string());
+    }
+    Ok(())
+}
+
+// Keeps a new game of `client` and returns its id, unless there are too many games already
+pub fn insert(mut game: Game, ranking: Ranking, client: &str) -> Result<String, String> {
+    // the server never shows the posterior, so it doesn't keep it up to date
+    game.update_belief = false;
+    let id = format!("{:016x}", rand::thread_rng().gen::<u64>());
+    let mut games = games();
+    make_room(&mut games, client, MAX_GAMES, MAX_GAMES_PER_CLIENT)?;
+    games.insert(
+        id.clone(),
+        Entry {
+            game,
+            history: vec![],
+            touched: Instant::now(),
+            ranking,
+            submitted: false,
+            client: client.to_string(),
+        },
+    );
+    Ok(id)
+}
+
+// None when there is no game with that id
+pub fn with_game<T>(id: &str, f: impl FnOnce(&Game, &[Event]) -> T) -> Option<T> {
+    let games = games();
+    let entry = games.get(id)?;
+    Some(f(&entry.game, &entry.history))
+}
+
+// Plays the action on the game and records what it revealed. `f` sees the game after the action.
+pub fn play<T>(id: &str, action: Action, f: impl FnOnce(&Game, Event) -> T) -> Option<Result<T, String>> {
+    let mut games = games();
+    let entry = games.get_mut(id)?;
+    entry.touched = Instant::now();
+    Some(entry.game.apply(action).map(|event| {
+        entry.history.push(event.clone());
+        f(&entry.game, event)
+    }))
+}
+
+// Hands a game to the leaderboard once. `f` sees the game and the date of its daily challenge, the
+// game only counts as submitted if it accepts it.
+pub fn submit<T>(id: &str, f: impl FnOnce(&Game, Option<&str>) -> Result<T, String>) -> Result<T, String> {
+    let mut games = games();
+    let entry = games.get_mut(id).ok_or("No such game, it may have expired")?;
+    let daily = match &entry.ranking {
+        Ranking::Unranked => return Err("Games on a seed of the player's choosing aren't ranked".to_string()),
+        _ if entry.submitted => return Err("This game was already submitted".to_string()),
+        Ranking::Ranked => None,
+        Ranking::Daily(date) => Some(date.as_str()),
+    };
+    let result = f(&entry.game, daily)?;
+    entry.submitted = true;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::Preset;
+
+    fn finished_game() -> Game {
+        let mut game = Game::from_seed(Preset::Normal.settings(), 5);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        let (x, y) = game.ghost_position;
+        game.apply(Action::Bust { x, y }).unwrap();
+        game
+    }
+
+    #[test]
+    fn server_games_are_submitted_once() {
+        let id = insert(finished_game(), Ranking::Ranked, "test").unwrap();
+        assert_eq!(submit(&id, |game, daily| Ok((game.seed, daily.is_none()))), Ok((5, true)));
+        assert!(submit(&id, |_, _| Ok(())).is_err());
+    }
+
+    #[test]
+    fn daily_games_are_submitted_with_their_date() {
+        let id = insert(finished_game(), Ranking::Daily("2024-05-01".to_string()), "test").unwrap();
+        assert_eq!(submit(&id, |_, daily| Ok(daily.map(str::to_string))), Ok(Some("2024-05-01".to_string())));
+    }
+
+    #[test]
+    fn unknown_ids_and_client_seeds_are_rejected() {
+        assert!(submit("not a game", |_, _| Ok(())).is_err());
+        let id = insert(finished_game(), Ranking::Unranked, "test").unwrap();
+        assert!(submit(&id, |_, _| Ok(())).is_err());
+    }
+
+    #[test]
+    fn rejected_submissions_can_be_sent_again() {
+        let id = insert(finished_game(), Ranking::Ranked, "test").unwrap();
+        assert!(submit(&id, |_, _| Err::<(), _>("bad name".to_string())).is_err());
+        assert!(submit(&id, |_, _| Ok(())).is_ok());
+    }
+
+    fn entry(status: Status, client: &str, age: u64) -> Entry {
+        let mut game = finished_game();
+        game.status = status;
+        Entry {
+            game,
+            history: vec![],
+            touched: Instant::now() - Duration::from_secs(age),
+            ranking: Ranking::Ranked,
+            submitted: false,
+            client: client.to_string(),
+        }
+    }
+
+    #[test]
+    fn clients_have_a_cap_on_unfinished_games() {
+        let mut games = HashMap::new();
+        games.insert("a".to_string(), entry(Status::Playing, "bot", 0));
+        games.insert("b".to_string(), entry(Status::Playing, "bot", 0));
+        games.insert("c".to_string(), entry(Status::Won, "bot", 0));
+        assert!(make_room(&mut games, "bot", 10, 2).is_err());
+        assert!(make_room(&mut games, "player", 10, 2).is_ok());
+        games.get_mut("a").unwrap().game.status = Status::Lost;
+        assert!(make_room(&mut games, "bot", 10, 2).is_ok());
+    }
+
+    #[test]
+    fn a_full_server_drops_the_oldest_finished_games() {
+        let mut games = HashMap::new();
+        games.insert("old".to_string(), entry(Status::Won, "a", 60));
+        games.insert("new".to_string(), entry(Status::Lost, "a", 0));
+        games.insert("playing".to_string(), entry(Status::Playing, "b", 120));
+        assert!(make_room(&mut games, "c", 3, 5).is_ok());
+        let mut ids = games.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, ["new", "playing"]);
+        // with only unfinished games there is nothing to drop
+        games.remove("new");
+        games.insert("other".to_string(), entry(Status::Playing, "d", 0));
+        assert!(make_room(&mut games, "c", 2, 5).is_err());
+        assert_eq!(games.len(), 2);
+    }
+
+    #[test]
+    fn expired_games_make_room() {
+        let mut games = HashMap::new();
+        games.insert("stale".to_string(), entry(Status::Playing, "bot", EXPIRY.as_secs() + 1));
+        assert!(make_room(&mut games, "bot", 1, 1).is_ok());
+        assert!(games.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+// The game text in every language it is translated to. The catalogues in `locales/` are built
+// into the binary, keys missing from one fall back to English, then to the key itself.
+use crate::game::SensorColor;
+use gloo::storage::{LocalStorage, Storage};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const LOCALE_KEY: &str = "bust-ghost.locale";
+
+const CATALOGUES: [&str; 3] = [
+    include_str!("../locales/en.json"),
+    include_str!("../locales/fr.json"),
+    include_str!("../locales/ar.json"),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    Ar,
+}
+
+impl Locale {
+    // In the order of `CATALOGUES`
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::Fr, Locale::Ar];
+
+    // The language tag, for `lang` and `Intl`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+            Locale::Ar => "ar",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Locale> {
+        Locale::ALL.into_iter().find(|locale| locale.name() == name)
+    }
+
+    // The name of the language in itself, for the language picker
+    pub fn label(&self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Fr => "Français",
+            Locale::Ar => "العربية",
+        }
+    }
+
+    pub fn dir(&self) -> &'static str {
+        match self {
+            Locale::Ar => "rtl",
+            _ => "ltr",
+        }
+    }
+
+    fn catalogue(&self) -> &'static HashMap<String, String> {
+        static PARSED: OnceLock<Vec<HashMap<String, String>>> = OnceLock::new();
+        let parsed = PARSED.get_or_init(|| {
+            CATALOGUES
+                .iter()
+                .map(|catalogue| serde_json::from_str(catalogue).expect("the catalogues are valid JSON"))
+                .collect()
+        });
+        &parsed[*self as usize]
+    }
+}
+
+// The message `key` in `locale`
+pub fn t(locale: Locale, key: &str) -> String {
+    locale
+        .catalogue()
+        .get(key)
+        .or_else(|| Locale::En.catalogue().get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+// The message `key` with each `{name}` replaced by its value in `args`
+pub fn tr(locale: Locale, key: &str, args: &[(&str, String)]) -> String {
+    let mut message = t(locale, key);
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), value);
+    }
+    message
+}
+
+// The name of a reading's color, "not sensed" without one
+pub fn color(locale: Locale, color: Option<SensorColor>) -> String {
+    match color {
+        Some(color) => t(locale, &format!("color.{}", color.name())),
+        None => t(locale, "cell.not_sensed"),
+    }
+}
+
+// The short label of a direction `relative_direction` gave, like "NE"
+pub fn direction(locale: Locale, direction: &str) -> String {
+    t(locale, &format!("direction.{}", direction))
+}
+
+// The arrow shown next to the direction, from the catalogue so a locale or theme can draw its own
+pub fn direction_arrow(locale: Locale, direction: &str) -> String {
+    t(locale, &format!("direction.arrow.{}", direction))
+}
+
+// Digits, decimal separator and percent sign as the locale writes them. Worked out here rather than
+// with `Intl` so that the server and the hydrating client write the same numbers.
+fn localize_digits(locale: Locale, number: &str) -> String {
+    match locale {
+        Locale::En => number.to_string(),
+        Locale::Fr => number.replace('.', ","),
+        Locale::Ar => number
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(digit) => char::from_u32('٠' as u32 + digit).unwrap_or(c),
+                None if c == '.' => '٫',
+                None => c,
+            })
+            .collect(),
+    }
+}
+
+pub fn number(locale: Locale, value: impl ToString) -> String {
+    localize_digits(locale, &value.to_string())
+}
+
+pub fn decimal(locale: Locale, value: f64, decimals: usize) -> String {
+    localize_digits(locale, &format!("{:.*}", decimals, value))
+}
+
+// `fraction` as a percentage, 0.1234 is "12.34%" in English
+pub fn percent(locale: Locale, fraction: f64, decimals: usize) -> String {
+    let value = decimal(locale, fraction * 100.0, decimals);
+    match locale {
+        Locale::En => format!("{}%", value),
+        // with a narrow no-break space
+        Locale::Fr => format!("{}\u{202f}%", value),
+        Locale::Ar => format!("{}٪", value),
+    }
+}
+
+// The language picked by the player, shared by `App` with every page
+pub fn use_locale() -> RwSignal<Locale> {
+    use_context::<RwSignal<Locale>>().unwrap_or_else(|| create_rw_signal(Locale::default()))
+}
+
+// The saved language, or the browser's when it is one of ours
+pub fn load() -> Locale {
+    LocalStorage::get(LOCALE_KEY).ok().unwrap_or_else(|| {
+        let language = window().navigator().language().unwrap_or_default();
+        Locale::from_name(language.split('-').next().unwrap_or_default()).unwrap_or_default()
+    })
+}
+
+pub fn save(locale: Locale) {
+    if let Err(err) = LocalStorage::set(LOCALE_KEY, locale) {
+        gloo::console::error!(format!("Could not save the language: {}", err));
+    }
+}
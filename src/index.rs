@@ -1,119 +1,704 @@
+use crate::agents;
+use crate::board::{cell_label, column_label, BoardSignals, CellSignals};
+use crate::canvas_board::{CanvasBoard, CANVAS_ABOVE};
+use crate::clipboard;
+use crate::daily;
 use crate::game::*;
-use gloo::console::log;
+use crate::i18n::{self, t, tr, use_locale, Locale};
+// leptos has its own `Action`
+use crate::game::Action;
+use crate::leaderboard::Submission;
+use crate::leaderboard_view::SubmitResult;
+use crate::link;
+use crate::online;
+use crate::palette::{self, glyph, Appearance, Palette, Theme};
+use crate::preset::Preset;
+use crate::scores;
+use crate::stats::{self, GameRecord};
+use crate::worker::{EngineInput, EngineOutput, EngineWorker, Json, WORKER_ABOVE, WORKER_PATH};
+use gloo::worker::{Spawnable, WorkerBridge};
+use leptos::html::Div;
 use leptos::*;
+use leptos_router::{use_query_map, A};
 // use lepto p
 
+// Boards with more cells than this only render the cells that are scrolled into view
+pub const VIRTUALISE_ABOVE: i32 = 2500;
+// Size in pixels of a cell on a virtualised board
+const VIRTUAL_CELL_SIZE: i32 = 36;
+// Size in pixels of the row and column headers of a virtualised board
+const VIRTUAL_HEADER_SIZE: i32 = 28;
+
+// The accessible name of a cell: where it is, what it read and, when peeping, how likely the ghost is there
+pub fn describe_cell(locale: Locale, x: i32, y: i32, color: Option<SensorColor>, probability: Option<f64>) -> String {
+    let mut description = format!("{}, {}", cell_label(x, y), i18n::color(locale, color));
+    if let Some(probability) = probability {
+        description += &format!(", {}", i18n::percent(locale, probability, 2));
+    }
+    description
+}
+
+fn hint_text(locale: Locale, action: Action) -> String {
+    let (x, y) = action.cell();
+    let key = match action {
+        Action::Sense { .. } => "hint.sense",
+        Action::Bust { .. } => "hint.bust",
+    };
+    tr(locale, key, &[("x", i18n::number(locale, x)), ("y", i18n::number(locale, y))])
+}
+
+// What the cell shows, for the stylesheet: `cell--unsensed` or `cell--sensed-<color>`, and
+// `cell--selected` under the cursor
+pub fn cell_class(color: Option<SensorColor>, selected: bool) -> String {
+    let mut class = match color {
+        Some(color) => format!("cell cell--sensed-{}", color.name()),
+        None => "cell cell--unsensed".to_string(),
+    };
+    if selected {
+        class += " cell--selected";
+    }
+    class
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+    #[default]
+    Free,
+    // The day's challenge: a game seeded from the date that can only be played once a day
+    Daily,
+    // The game described by the query of a shared `/play` link
+    Link,
+    // A game played against the server, which keeps the ghost to itself
+    Online,
+    // The day's ranked challenge, played against the server on the daily settings
+    RankedDaily,
+}
+
 #[component]
-pub fn GameView() -> impl IntoView {
+pub fn GameView(
+    #[prop(optional)] mode: Mode,
+    // Boards with more cells than this are drawn on a canvas, `CANVAS_ABOVE` by default
+    #[prop(optional)] canvas_above: Option<i32>,
+    // Whether to play in the engine worker, by default for boards of more than `WORKER_ABOVE`
+    // cells in the Trunk build, which is the one that builds the worker
+    #[prop(optional)] worker: Option<bool>,
+) -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
 
-    let game = Game::new(9, 12);
+    let daily = mode == Mode::Daily;
+    let online = matches!(mode, Mode::Online | Mode::RankedDaily);
+    let ranked_daily = mode == Mode::RankedDaily;
+    let locale = use_locale();
+    let mut notices = vec![];
+    let game = match mode {
+        // seeded from the date once mounted
+        Mode::Daily | Mode::RankedDaily => Game::from_settings(daily::settings()),
+        Mode::Link => {
+            let play_link = use_query_map().with_untracked(|query| link::parse(|key| query.get(key).cloned()));
+            notices = play_link.notices.iter().map(|notice| notice.message(locale.get_untracked())).collect();
+            play_link.game()
+        }
+        Mode::Free | Mode::Online => Game::from_settings(GameSettings::default()),
+    };
     // create a signal to update the game state
     let (gm, set_game) = create_signal(game);
+    let (show_settings, set_show_settings) = create_signal(false);
+    let (daily_date, set_daily_date) = create_signal(None::<String>);
+    let (high_score, set_high_score) = create_signal(None);
     let (peeping, set_peeping) = create_signal(true);
-    let (button_text, set_button_text) = create_signal("Hide");
+    let (button_text, set_button_text) = create_signal("button.hide");
     let (clicked_cell, set_clicked_cell) = create_signal((0, 0));
     let (state, set_state) = create_signal(-1);
+    let (share, set_share) = create_signal(None::<String>);
+    let (copied, set_copied) = create_signal(false);
+    let (notices, set_notices) = create_signal(notices);
+    let (link_copied, set_link_copied) = create_signal(false);
     let (direction_hint, set_direction_hint) = create_signal("".to_string());
+    let (started_at, set_started_at) = create_signal(0.0);
+    let (online_id, set_online_id) = create_signal(None::<String>);
+    // Every action played so far, sent with the seed when the game is submitted to the leaderboard
+    let (actions, set_actions) = create_signal(Vec::<Action>::new());
+    // Practice games can be undone, so they count for no statistics, scores or leaderboard
+    let (practice, set_practice) = create_signal(false);
+    let (hint, set_hint) = create_signal(None::<Action>);
+    let (show_help, set_show_help) = create_signal(false);
+    // Read out by screen readers: the readings, the busts, the outcome and where the cursor is
+    let (announcement, set_announcement) = create_signal(String::new());
+    // The text of `key` in the player's language, for the view
+    let l10n = move |key: &'static str| move || t(locale.get(), key);
+    // The palette, glyphs and theme, loaded by `App` once mounted
+    let appearance = use_context::<RwSignal<Appearance>>().unwrap_or_else(|| create_rw_signal(Appearance::default()));
     // compute initial game state
     set_game.update(|game| {
-        game.place_ghost();
+        // online, the ghost is only placed on the server
+        if !online {
+            game.place_ghost();
+        }
         game.compute_initial_prior_probabilities();
     });
+    // The cells render from these, so that playing doesn't rebuild the whole board
+    let canvas_above = canvas_above.unwrap_or(CANVAS_ABOVE);
+    let board = gm.with_untracked(|game| BoardSignals::new(game, canvas_above.max(0) as usize));
+    create_effect(move |_| gm.with(|game| board.sync(game)));
+    let selected = store_value(create_selector(move || clicked_cell.get()));
+    let show_probabilities = create_memo(move |_| peeping.get() && gm.with(|game| game.settings.allow_peep));
 
-    let handle_peep = move |_| {
+    let toggle_peep = move || {
         set_peeping.update(|peeping| *peeping = !*peeping);
         set_button_text.update(|text| {
-            if *text == "Peep" {
-                *text = "Hide";
+            if *text == "button.peep" {
+                *text = "button.hide";
             } else {
-                *text = "Peep";
+                *text = "button.peep";
             }
         });
     };
+    let handle_peep = move |_| toggle_peep();
 
-    let handle_bust = move |_| {
+    let finish = move |game: &Game, won: bool| {
+        if practice.get_untracked() {
+            return;
+        }
+        let mut record = GameRecord::new(game, won, started_at.get_untracked(), js_sys::Date::now());
+        record.daily = daily_date.get_untracked();
+        stats::record(record);
+        if let Some(date) = daily_date.get_untracked() {
+            set_share.set(Some(daily::record(&date, game, won, appearance.get_untracked(), locale.get_untracked()).share));
+        }
+    };
+
+    // Shows what an action revealed, whether it was played here or on the server
+    let show_event = move |game: &Game, event: Event| {
+        if let Event::Reading { direction, .. } = &event {
+            set_direction_hint.set(direction.clone());
+        }
+        let locale = locale.get_untracked();
+        let message = match &event {
+            Event::Reading { x, y, color, direction } => {
+                let cell = ("cell", cell_label(*x, *y));
+                let color = ("color", i18n::color(locale, SensorColor::from_name(color)));
+                match Direction::from_name(direction) {
+                    Some(Direction::Bingo) => tr(locale, "announce.reading_here", &[cell, color]),
+                    Some(direction) => {
+                        let direction = ("direction", t(locale, &format!("direction.words.{}", direction.name())));
+                        tr(locale, "announce.reading", &[cell, color, direction])
+                    }
+                    None => tr(locale, "announce.reading_plain", &[cell, color]),
+                }
+            }
+            Event::Bust { x, y, hit: true } => tr(locale, "announce.hit", &[("cell", cell_label(*x, *y))]),
+            Event::Bust { x, y, hit: false } => tr(
+                locale,
+                "announce.miss",
+                &[("cell", cell_label(*x, *y)), ("busts", i18n::number(locale, game.busts))],
+            ),
+        };
+        let message = match game.status {
+            Status::Won => tr(locale, "announce.won", &[("message", message)]),
+            Status::Lost => {
+                let (x, y) = game.ghost_position;
+                tr(locale, "announce.lost", &[("message", message), ("cell", cell_label(x, y))])
+            }
+            Status::Playing => message,
+        };
+        set_announcement.set(message);
+        match game.status {
+            Status::Won => {
+                set_state.set(1);
+                finish(game, true);
+                if let (false, Some(preset)) = (practice.get_untracked(), game.settings.preset) {
+                    if scores::record_win(preset, game.score) {
+                        set_high_score.set(Some(game.score));
+                    }
+                }
+            }
+            Status::Lost => {
+                set_state.set(0);
+                finish(game, false);
+            }
+            Status::Playing => {
+                if let Event::Bust { .. } = event {
+                    set_state.set(-2);
+                }
+            }
+        }
+    };
+
+    // The day's challenge is saved from its first action on, see `daily::progress`
+    let push_action = move |action: Action| {
+        set_actions.update(|actions| actions.push(action));
+        if let Some(date) = daily_date.get_untracked() {
+            actions.with_untracked(|actions| daily::save_progress(&date, actions));
+        }
+    };
+
+    // Applies what an action revealed to the game shown here, when it was played by the server or
+    // the engine worker
+    let mirror = move |game: &mut Game, action: Action, event: &Event, score: i32, busts: i32, status: Status| {
+        push_action(action);
+        match event {
+            Event::Reading { x, y, color, direction } => game.observe(*x, *y, color, direction),
+            Event::Bust { x, y, hit: false } if game.update_belief => game.rule_out(*x, *y),
+            Event::Bust { .. } => {}
+        }
+        game.score = score;
+        game.busts = busts;
+        game.status = status;
+    };
+
+    // The engine worker, which keeps the posterior of boards too big to update on this thread.
+    // Outputs about an earlier game are dropped.
+    let engine = store_value(None::<WorkerBridge<EngineWorker>>);
+    let engine_game = store_value(0u32);
+    let send = move |input: EngineInput| engine.with_value(|engine| engine.as_ref().map(|engine| engine.send(input)));
+    // Moves the cursor onto the cell of the hinted action and says what it is
+    let show_hint = move |action: Action| {
+        let (x, y) = action.cell();
+        set_clicked_cell.set((x, y));
+        let locale = locale.get_untracked();
+        set_announcement.set(tr(
+            locale,
+            "announce.hint",
+            &[("hint", hint_text(locale, action)), ("cell", cell_label(x, y))],
+        ));
+        set_hint.set(Some(action));
+    };
+    let on_engine_output = move |output: EngineOutput| match output {
+        EngineOutput::Played { game: id, action, event, score, busts, status } if id == engine_game.get_value() => {
+            set_game.update(|game| {
+                mirror(game, action, &event, score, busts, status);
+                show_event(game, event);
+            });
+        }
+        EngineOutput::Rejected { game: id, error, .. } if id == engine_game.get_value() => {
+            set_notices.set(vec![tr(locale.get_untracked(), "notice.play_failed", &[("error", error)])]);
+        }
+        EngineOutput::Hint { game: id, action } if id == engine_game.get_value() && state.get_untracked() < 0 => {
+            show_hint(action)
+        }
+        EngineOutput::Undecodable(error) => {
+            set_notices.set(vec![tr(locale.get_untracked(), "notice.engine_failed", &[("error", error)])]);
+        }
+        EngineOutput::Posterior { game: id, heatmap, .. } if id == engine_game.get_value() => {
+            set_game.update(|game| game.belief.spread(&heatmap));
+        }
+        _ => {}
+    };
+    // Hands the game shown here to the worker if it should play there. Called whenever a game starts.
+    let start_engine = move || {
         set_game.update(|game| {
-            let result = game.bust_ghost(clicked_cell.get().0, clicked_cell.get().1);
-            if result == 0 {
-                set_state.update(|state| *state = 0);
-                log!("Out of busts! You lose!");
-            } else if result == 1 {
-                set_state.update(|state| *state = 1);
-                log!("You win!");
+            game.update_belief = !worker.unwrap_or(cfg!(feature = "csr") && game.cells.len() as i32 > WORKER_ABOVE);
+            if game.update_belief {
+                return;
+            }
+            if engine.with_value(Option::is_none) {
+                let bridge = EngineWorker::spawner().callback(on_engine_output).encoding::<Json>().spawn(WORKER_PATH);
+                engine.set_value(Some(bridge));
+            }
+            engine_game.update_value(|id| *id += 1);
+            send(EngineInput::Start {
+                game: engine_game.get_value(),
+                settings: game.settings.clone(),
+                seed: game.seed,
+                place_ghost: !online,
+            });
+        });
+    };
+
+    let start_online = move |settings: GameSettings| {
+        set_online_id.set(None);
+        spawn_local(async move {
+            let started = if ranked_daily {
+                online::new_daily_game().await
             } else {
-                set_state.update(|state| *state = -2);
-                log!("Missed!");
+                let preset = link::base_preset(&settings).unwrap_or(Preset::Normal);
+                online::new_online_game(preset, settings.width, settings.height).await
+            };
+            match started {
+                Ok(started) => {
+                    let mut game = Game::from_settings(started.settings);
+                    game.compute_initial_prior_probabilities();
+                    set_game.set(game);
+                    start_engine();
+                    set_actions.set(vec![]);
+                    set_online_id.set(Some(started.id));
+                }
+                Err(err) => set_notices.set(vec![tr(
+                    locale.get_untracked(),
+                    "notice.start_failed",
+                    &[("error", err.to_string())],
+                )]),
             }
         });
     };
 
-    let cells = move || {
-        gm.get().grid.iter().flat_map(|row| row.iter()).map(|cell| {
-            let color = cell.color.clone();
-            let probability = cell.probability;
-            let x = cell.x;
-            let y = cell.y;
-            let clicked = clicked_cell.clone();
-            let set_clicked = set_clicked_cell.clone();
-            view! {
-                <button
-                    style=format!("background-color: {}; border: 1px solid black;display: flex; align-items: center; justify-content: center; cursor: pointer", color)
-                    style:border= move || format!("1px solid {}", if clicked.get() == (x, y) {"red"} else {"black"})
-                    on:click=move |_| {
-                        set_clicked.update(|clicked| *clicked = (x, y));
-                        set_game.update(|game| {
-                            log!(&format!("Clicked: {}, {}", x, y));
-                            let (color, direction) = game.distance_sense(x, y);
-                            set_direction_hint.update(|hint| *hint = direction.clone());
-                            log!(&format!("Color: {}, Direction: {}", color, direction.clone()));
-                            if game.grid[x as usize][y as usize].visited {
-                                return;
-                            }
-                            game.grid[x as usize][y as usize].visited = true;
-                            log!(&format!("Bust: {}, {}", x, y));
-                            if game.score == 0 {
-                                set_state.update(|state| *state = 0);
-                                log!("Out of attempts! You lose!");
-                            }
-                            game.grid[x as usize][y as usize].color = color.clone();
-                            game.update_posterior_ghost_location_probabilities(color, x, y, direction);
-                        });
+    // The date and local storage only exist in the browser, so they are read once mounted. This
+    // way the server and the hydrating client render the same page.
+    create_effect(move |_| {
+        set_started_at.set(js_sys::Date::now());
+        if daily {
+            let date = daily::today();
+            set_daily_date.set(Some(date.clone()));
+            // players only get one attempt a day
+            if let Some(result) = daily::result(&date) {
+                set_state.set(if result.won { 1 } else { 0 });
+                set_share.set(Some(result.share));
+            }
+            // a game left halfway is played again up to where it was
+            let saved = daily::progress(&date);
+            set_game.update(|game| {
+                game.reseed(daily::seed(&date));
+                game.place_ghost();
+                let mut last = None;
+                for action in &saved {
+                    last = game.apply(*action).ok();
+                }
+                if let Some(event) = last {
+                    show_event(game, event);
+                }
+            });
+            set_actions.set(saved);
+        }
+        if online {
+            start_online(gm.with_untracked(|game| game.settings.clone()));
+        } else {
+            start_engine();
+        }
+        set_high_score.set(gm.with_untracked(|game| game.settings.preset).and_then(scores::high_score));
+    });
+
+    // Nothing is played under the result, which is also up when the day's challenge was already
+    // played, nor once the game is over
+    let playing = move || state.get_untracked() < 0 && gm.with_untracked(|game| game.status == Status::Playing);
+
+    let play = move |action: Action| {
+        if !playing() {
+            return;
+        }
+        set_hint.set(None);
+        if !online {
+            if gm.with_untracked(|game| !game.update_belief) {
+                send(EngineInput::Play(action));
+                return;
+            }
+            set_game.update(|game| {
+                if let Ok(event) = game.apply(action) {
+                    push_action(action);
+                    show_event(game, event);
+                }
+            });
+            return;
+        }
+        // still waiting for the server to start the game
+        let Some(id) = online_id.get_untracked() else {
+            return;
+        };
+        spawn_local(async move {
+            let turn = match action {
+                Action::Sense { x, y } => online::sense_online(id, x, y).await,
+                Action::Bust { x, y } => online::bust_online(id, x, y).await,
+            };
+            match turn {
+                Ok(turn) => set_game.update(|game| {
+                    mirror(game, action, &turn.event, turn.score, turn.busts, turn.status);
+                    if !game.update_belief {
+                        send(EngineInput::Observe(turn.event.clone()));
                     }
-                >
-                    {if peeping.get() {
-                        format!("{:.2}%", probability*100.0)
-                    } else {
-                        "".to_string()
-                    }}
-                </button>
+                    if let Some(reveal) = turn.reveal {
+                        game.seed = reveal.seed;
+                        game.ghost_position = reveal.ghost_position;
+                    }
+                    show_event(game, turn.event);
+                }),
+                Err(err) => set_notices.set(vec![tr(
+                    locale.get_untracked(),
+                    "notice.server_failed",
+                    &[("error", err.to_string())],
+                )]),
             }
-        }).collect::<Vec<_>>()
+        });
     };
 
-    view! {
-        // injects a stylesheet into the document <head>
-        // id=leptos means cargo-leptos will hot-reload this stylesheet
+    let handle_bust = move |_| {
+        let (x, y) = clicked_cell.get();
+        play(Action::Bust { x, y });
+    };
+
+    // Hints read the posterior, so they are only given in free play and practice, and only when
+    // the settings let the player peek at the probabilities anyway
+    let hints_allowed = move || (mode == Mode::Free || practice.get()) && gm.with(|game| game.settings.allow_peep);
+
+    // What `agents::hint` would play next, worked out by the worker when the game plays there
+    let ask_hint = move || {
+        if !playing() || !untrack(hints_allowed) {
+            return;
+        }
+        if gm.with_untracked(|game| !game.update_belief) {
+            send(EngineInput::Hint);
+            return;
+        }
+        show_hint(gm.with_untracked(agents::hint));
+    };
+
+    // Practice games played here can take back their last action: the game is replayed from its
+    // seed without it, which draws the same readings
+    let can_undo = move || practice.get() && !online && gm.with(|game| game.update_belief) && actions.with(|actions| !actions.is_empty());
+    let undo = move || {
+        if !untrack(can_undo) {
+            return;
+        }
+        let mut actions = actions.get_untracked();
+        actions.pop();
+        let mut game = gm.with_untracked(|game| Game::from_seed(game.settings.clone(), game.seed));
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        for action in &actions {
+            game.apply(*action).ok();
+        }
+        set_game.set(game);
+        set_actions.set(actions);
+        set_state.set(-1);
+        set_direction_hint.set("".to_string());
+        set_hint.set(None);
+        set_announcement.set(t(locale.get_untracked(), "announce.undo"));
+    };
+
+    // Arrow keys or WASD move the cursor, which is the selected cell
+    let move_cursor = move |dx: i32, dy: i32| {
+        let (rows, columns) = gm.with_untracked(|game| (game.rows(), game.columns()));
+        set_clicked_cell.update(|(x, y)| {
+            *x = (*x + dx).clamp(0, rows - 1);
+            *y = (*y + dy).clamp(0, columns - 1);
+        });
+        let (x, y) = clicked_cell.get_untracked();
+        let description = gm.with_untracked(|game| {
+            let probability = show_probabilities.get_untracked().then(|| game.probability(x, y));
+            describe_cell(locale.get_untracked(), x, y, game.cell(x, y).color, probability)
+        });
+        set_announcement.set(description);
+    };
+    let handle_key = move |ev: ev::KeyboardEvent| {
+        if ev.ctrl_key() || ev.meta_key() || ev.alt_key() || show_settings.get_untracked() || !playing() {
+            return;
+        }
+        // typing in a form field
+        let focus = document().active_element().map(|element| element.tag_name()).unwrap_or_default();
+        if matches!(focus.as_str(), "INPUT" | "SELECT" | "TEXTAREA") {
+            return;
+        }
+        let key = ev.key();
+        if show_help.get_untracked() {
+            if matches!(key.as_str(), "?" | "Escape") {
+                set_show_help.set(false);
+            }
+            return;
+        }
+        match key.as_str() {
+            "ArrowUp" | "w" | "W" => move_cursor(-1, 0),
+            "ArrowDown" | "s" | "S" => move_cursor(1, 0),
+            "ArrowLeft" | "a" | "A" => move_cursor(0, -1),
+            "ArrowRight" | "d" | "D" => move_cursor(0, 1),
+            " " | "Enter" => {
+                let (x, y) = clicked_cell.get_untracked();
+                play(Action::Sense { x, y });
+            }
+            "b" | "B" => {
+                let (x, y) = clicked_cell.get_untracked();
+                play(Action::Bust { x, y });
+            }
+            "p" | "P" => {
+                if gm.with_untracked(|game| game.settings.allow_peep) {
+                    toggle_peep();
+                }
+            }
+            "h" | "H" => ask_hint(),
+            "u" | "U" => undo(),
+            "?" => set_show_help.set(true),
+            _ => return,
+        }
+        // keeps the page from scrolling and focused buttons from being clicked
+        ev.prevent_default();
+    };
+    let key_listener = window_event_listener(ev::keydown, handle_key);
+    on_cleanup(move || key_listener.remove());
+
+    let sense = move |x: i32, y: i32| {
+        set_clicked_cell.update(|clicked| *clicked = (x, y));
+        play(Action::Sense { x, y });
+    };
+
+    let cell_view = move |cell: CellSignals, position: String| {
+        let CellSignals { x, y, color, probability } = cell;
+        // only changes the text when the rounded probability does
+        let text = create_memo(move |_| {
+            if show_probabilities.get() {
+                i18n::percent(locale.get(), probability.get(), 2)
+            } else {
+                "".to_string()
+            }
+        });
+        let label = create_memo(move |_| {
+            describe_cell(locale.get(), x, y, color.get(), show_probabilities.get().then(|| probability.get()))
+        });
+        view! {
+            <button
+                role="gridcell"
+                aria-colindex=y + 2
+                aria-label=label
+                aria-selected=move || selected.with_value(|selected| selected.selected((x, y))).to_string()
+                class=move || cell_class(color.get(), selected.with_value(|selected| selected.selected((x, y))))
+                style=position
+                on:click=move |_| sense(x, y)
+            >
+                {move || (appearance.with(|appearance| appearance.glyphs) && color.get().is_some()).then(|| glyph(color.get()).to_string())}
+                {text}
+            </button>
+        }
+    };
+
+    // The header row of column names, then each row of cells after its number
+    let grid_rows = move || {
+        let columns = board.columns.get();
+        let header = view! {
+            <div role="row" aria-rowindex=1 class="board__row">
+                <div></div>
+                {(0..columns).map(|y| view! {
+                    <div role="columnheader" aria-colindex=y + 2 class="board__column-header">{column_label(y)}</div>
+                }).collect::<Vec<_>>()}
+            </div>
+        };
+        let rows = board.cells.with(|cells| {
+            cells
+                .chunks(columns.max(1) as usize)
+                .enumerate()
+                .map(|(x, row)| view! {
+                    <div role="row" aria-rowindex=x + 2 class="board__row">
+                        <div role="rowheader" aria-colindex=1 class="board__row-header">{x + 1}</div>
+                        {row.iter().map(|cell| cell_view(*cell, "".to_string())).collect::<Vec<_>>()}
+                    </div>
+                })
+                .collect::<Vec<_>>()
+        });
+        (header, rows)
+    };
+
+    let board_view = move || {
+        let (rows, columns) = (board.rows.get(), board.columns.get());
+        if rows * columns > canvas_above {
+            view! {
+                <CanvasBoard game=gm selected=clicked_cell show_probabilities=show_probabilities appearance=appearance.read_only() on_select=sense/>
+            }.into_view()
+        } else if rows * columns > VIRTUALISE_ABOVE {
+            view! {
+                <VirtualBoard board=board cell_view=cell_view/>
+            }.into_view()
+        } else {
+            view! {
+                <div
+                    role="grid"
+                    aria-label=l10n("board.label")
+                    aria-rowcount=rows + 1
+                    aria-colcount=columns + 1
+                    class="board"
+                    // east stays on the right in right-to-left languages
+                    dir="ltr"
+                    style=format!("grid-template-columns: auto repeat({}, 1fr); grid-template-rows: auto repeat({}, 1fr);", columns, rows)
+                >
+                    {grid_rows}
+                </div>
+            }.into_view()
+        }
+    };
+
+    // A won online game on a preset or the ranked daily challenge, which the server ranks once the
+    // actions played here replay to its own copy of the game
+    let submission = move || {
+        if state.get_untracked() != 1 {
+            return None;
+        }
+        let id = online_id.get_untracked()?;
+        let ranked = ranked_daily || gm.with_untracked(|game| game.settings.preset.is_some_and(|preset| game.settings == preset.settings()));
+        ranked.then(|| view! { <SubmitResult submission=Submission { name: String::new(), id, actions: actions.get_untracked() }/> })
+    };
 
-        // sets the document title
-        <div style="height: 100vh; display: flex; flex-direction: column; align-items: center; justify-content: center;">
+    let handle_copy_link = move |_| {
+        let origin = window().location().origin().unwrap_or_default();
+        let url = format!("{}/play?{}", origin, gm.with(link::query));
+        set_link_copied.set(clipboard::copy(&url));
+    };
+
+    let start_game = move |settings: GameSettings| {
+        set_high_score.set(settings.preset.and_then(scores::high_score));
+        if online {
+            start_online(settings);
+        } else {
+            set_actions.set(vec![]);
+            set_game.set(Game::from_settings(settings));
+            set_game.update(|game| {
+                game.place_ghost();
+                game.compute_initial_prior_probabilities();
+            });
+            start_engine();
+        }
+        set_clicked_cell.set((0, 0));
+        set_direction_hint.set("".to_string());
+        set_hint.set(None);
+        set_state.set(-1);
+        set_started_at.set(js_sys::Date::now());
+        set_link_copied.set(false);
+        set_notices.set(vec![]);
+        set_show_settings.set(false);
+    };
+
+    view! {
+        <div class="game" style=move || appearance.with(|appearance| appearance.palette.css_variables())>
             {
                 move || match state.get() {
                     0 | 1 => {
                         view! {
-                            <div style="position: absolute; background-color: rgba(0, 0, 0, 0.5); width: 100%; height: 100%; display: flex; align-items: center; justify-content: center;">
-                                <div style="background-color: white; padding: 20px; border-radius: 4px; display: flex; flex-direction: column; align-items: center;">
-                                    <h1 style="text-align: center;">{if state.get() == 0 {"You lose!"} else {"You win!"}}</h1>
-                                    <button on:click=move |_| {
-                                        set_state.update(|state| *state = -1);
-                                        set_game.update(|game| {
-                                            game.reset();
-                                            game.place_ghost();
-                                            game.compute_initial_prior_probabilities();
-                                        });
-                                    } style="padding: 10px;cursor: pointer; padding-left: 20px; padding-right: 20px; margin-top: 20px; background-color: green; color: white; border-radius: 4px; border: none; width: 200px; font-size: 20px;">
-                                        Play again
-                                    </button>
+                            <div class="overlay">
+                                <div class="dialog">
+                                    <h1>{move || t(locale.get(), if state.get() == 0 {"game.lost"} else {"game.won"})}</h1>
+                                    {move || share.get().map(|text| view! {
+                                        <pre class="share">{text.clone()}</pre>
+                                        <button on:click=move |_| set_copied.set(clipboard::copy(&text)) class="button button--primary">
+                                            {move || t(locale.get(), if copied.get() {"button.copied"} else {"button.copy_result"})}
+                                        </button>
+                                    })}
+                                    {submission()}
+                                    {move || can_undo().then(|| view! {
+                                        <button on:click=move |_| undo() class="button">
+                                            {l10n("button.undo")}
+                                        </button>
+                                    })}
+                                    {if daily {
+                                        view! {
+                                            <p>{l10n("game.come_back")}</p>
+                                            <A href="/">{l10n("link.free_play")}</A>
+                                        }.into_view()
+                                    } else {
+                                        view! {
+                                            <button on:click=move |_| {
+                                                set_state.update(|state| *state = -1);
+                                                set_hint.set(None);
+                                                set_started_at.set(js_sys::Date::now());
+                                                set_link_copied.set(false);
+                                                if online {
+                                                    start_online(gm.with_untracked(|game| game.settings.clone()));
+                                                } else {
+                                                    set_actions.set(vec![]);
+                                                    set_game.update(|game| {
+                                                        game.reset();
+                                                        game.place_ghost();
+                                                        game.compute_initial_prior_probabilities();
+                                                    });
+                                                    start_engine();
+                                                }
+                                            } class="button button--success">
+                                                {l10n("button.play_again")}
+                                            </button>
+                                        }.into_view()
+                                    }}
                                 </div>
                             </div>
                         }
@@ -121,52 +706,385 @@ pub fn GameView() -> impl IntoView {
                     _ => view! {<div></div>},
                 }
             }
-            <div style="display: flex; flex-direction: column; align-items: center;height: 80%; width: 80%">
-                <div style="margin-bottom: 20px; display: flex; flex-direction: column; align-items: center; gap: 10px;">
-                    <h1 style="text-align: center;">Bust The Ghost</h1>
-                    <p style="text-align: center;">Click on a cell to bust the ghost. The color of the cell will give you a clue about the ghosts location.</p>
-                    <p style="text-align: center;">Score: {move || gm.get().score} attempte left</p>
-                    <p style="text-align: center;">Busts: {move || gm.get().busts} left</p>
-                    {move || match state.get() {
-                        -2 => {
-                            view! {
-                                <p style="text-align: center; color: red;">Missed! Try again!</p>
-                            }
-                        },
-                        _ => {
-                            view! {
-                                <p style="text-align: center; color: white; user-select: none">{"a"}</p>
+            {move || if show_settings.get() {
+                view! {
+                    <SettingsDialog
+                        initial=gm.with_untracked(|game| game.settings.clone())
+                        on_start=start_game
+                        on_cancel=move || set_show_settings.set(false)
+                    />
+                }.into_view()
+            } else {
+                view! {}.into_view()
+            }}
+            {move || (!notices.with(Vec::is_empty)).then(|| view! {
+                <div class="notice">
+                    <ul>
+                        {notices.get().into_iter().map(|notice| view! { <li>{notice}</li> }).collect::<Vec<_>>()}
+                    </ul>
+                    <button on:click=move |_| set_notices.set(vec![]) class="small-button">{l10n("button.ok")}</button>
+                </div>
+            })}
+            <div role="status" aria-live="polite" aria-atomic="true" class="visually-hidden">{announcement}</div>
+            {move || show_help.get().then(|| view! {
+                <div class="overlay overlay--top">
+                    <div class="dialog">
+                        <h2>{l10n("help.title")}</h2>
+                        <table class="shortcuts">
+                            <tr><td>{l10n("help.move_keys")}</td><td>{l10n("help.move")}</td></tr>
+                            <tr><td>{l10n("help.sense_keys")}</td><td>{l10n("help.sense")}</td></tr>
+                            <tr><td>B</td><td>{l10n("help.bust")}</td></tr>
+                            <tr><td>P</td><td>{l10n("help.peep")}</td></tr>
+                            {move || hints_allowed().then(|| view! { <tr><td>H</td><td>{l10n("help.hint")}</td></tr> })}
+                            <tr><td>U</td><td>{l10n("help.undo")}</td></tr>
+                            <tr><td>"?"</td><td>{l10n("help.help")}</td></tr>
+                        </table>
+                        <button on:click=move |_| set_show_help.set(false) class="button button--primary">
+                            {l10n("button.close")}
+                        </button>
+                    </div>
+                </div>
+            })}
+            <div class="game__main">
+                <div class="game__header">
+                    <h1>
+                        {l10n("game.title")}
+                        {move || daily_date.get().map(|date| tr(locale.get(), "game.daily", &[("date", date)]))}
+                        {(online && !ranked_daily).then_some(l10n("game.online"))}
+                        {ranked_daily.then_some(l10n("game.ranked_daily"))}
+                    </h1>
+                    <p>{l10n("game.intro")}</p>
+                    <p>{move || tr(locale.get(), "game.score", &[("attempts", i18n::number(locale.get(), gm.with(|game| game.score)))])}</p>
+                    <p>{move || tr(locale.get(), "game.busts", &[("busts", i18n::number(locale.get(), gm.with(|game| game.busts)))])}</p>
+                    <p>
+                        {move || gm.with(|game| t(locale.get(), &game.settings.preset.map_or("preset.custom".to_string(), |preset| format!("preset.{}", preset.name()))))}
+                        {move || high_score.get().map(|score| tr(locale.get(), "game.best", &[("attempts", i18n::number(locale.get(), score))])).unwrap_or_default()}
+                    </p>
+                    <p class="game__missed">{move || (state.get() == -2).then(|| t(locale.get(), "game.missed"))}</p>
+                    <p class="game__direction">
+                        {move || {
+                            let direction = direction_hint.get();
+                            let locale = locale.get();
+                            (!direction.is_empty()).then(|| {
+                                format!("{} {}", i18n::direction(locale, &direction), i18n::direction_arrow(locale, &direction))
+                            })
+                        }}
+                    </p>
+                    {move || hint.get().map(|action| view! { <p class="game__hint">{move || hint_text(locale.get(), action)}</p> })}
+                </div>
+                {board_view}
+                <button on:click=handle_peep prop:disabled=move || !gm.with(|game| game.settings.allow_peep) class="button button--success">
+                    {move || t(locale.get(), button_text.get())}
+                </button>
+                <button on:click=handle_bust class="button button--danger">
+                    {move || {
+                        let (x, y) = clicked_cell.get();
+                        tr(locale.get(), "button.bust", &[("x", i18n::number(locale.get(), x)), ("y", i18n::number(locale.get(), y))])
+                    }}
+                </button>
+                {(!daily && !ranked_daily).then(|| view! {
+                    <button on:click=move |_| set_show_settings.set(true) class="button button--primary">
+                        {l10n("button.new_game")}
+                    </button>
+                })}
+                // the client doesn't know the seed of an online game until it's over
+                {(!daily && !online).then(|| view! {
+                    <button on:click=handle_copy_link class="button">
+                        {move || t(locale.get(), if link_copied.get() {"button.link_copied"} else {"button.copy_link"})}
+                    </button>
+                })}
+                {(!daily && !online).then(|| view! {
+                    <div class="game__toolbar">
+                        // only before the first action, so a game can't turn into practice to be undone
+                        <label>
+                            <input
+                                type="checkbox"
+                                prop:checked=practice
+                                prop:disabled=move || actions.with(|actions| !actions.is_empty())
+                                on:change=move |ev| set_practice.set(event_target_checked(&ev))
+                            />
+                            " "{l10n("label.practice")}
+                        </label>
+                        <button on:click=move |_| undo() prop:disabled=move || !can_undo() class="small-button">
+                            {l10n("button.undo")}
+                        </button>
+                        {move || hints_allowed().then(|| view! {
+                            <button on:click=move |_| ask_hint() class="small-button">
+                                {l10n("button.hint")}
+                            </button>
+                        })}
+                    </div>
+                })}
+                <div class="game__toolbar">
+                    <label>
+                        {l10n("label.colors")}" "
+                        <select on:change=move |ev| {
+                            let palette = Palette::from_name(&event_target_value(&ev)).unwrap_or_default();
+                            appearance.update(|appearance| appearance.palette = palette);
+                            palette::save(appearance.get_untracked());
+                        }>
+                            {Palette::ALL.into_iter().map(|palette| view! {
+                                <option value=palette.name() prop:selected=move || appearance.with(|appearance| appearance.palette == palette)>
+                                    {move || t(locale.get(), &format!("palette.{}", palette.name()))}
+                                </option>
+                            }).collect::<Vec<_>>()}
+                        </select>
+                    </label>
+                    <label>
+                        <input
+                            type="checkbox"
+                            prop:checked=move || appearance.with(|appearance| appearance.glyphs)
+                            on:change=move |ev| {
+                                appearance.update(|appearance| appearance.glyphs = event_target_checked(&ev));
+                                palette::save(appearance.get_untracked());
                             }
-                        }
+                        />
+                        " "{l10n("label.symbols")}
+                    </label>
+                    <label>
+                        {l10n("label.theme")}" "
+                        <select on:change=move |ev| {
+                            let theme = Theme::from_name(&event_target_value(&ev)).unwrap_or_default();
+                            appearance.update(|appearance| appearance.theme = theme);
+                            palette::save(appearance.get_untracked());
+                        }>
+                            {Theme::ALL.into_iter().map(|theme| view! {
+                                <option value=theme.name() prop:selected=move || appearance.with(|appearance| appearance.theme == theme)>
+                                    {move || t(locale.get(), &format!("theme.{}", theme.name()))}
+                                </option>
+                            }).collect::<Vec<_>>()}
+                        </select>
+                    </label>
+                    <label>
+                        {l10n("label.language")}" "
+                        <select on:change=move |ev| {
+                            let picked = Locale::from_name(&event_target_value(&ev)).unwrap_or_default();
+                            locale.set(picked);
+                            i18n::save(picked);
+                        }>
+                            {Locale::ALL.into_iter().map(|option| view! {
+                                <option value=option.name() lang=option.name() prop:selected=move || locale.get() == option>
+                                    {option.label()}
+                                </option>
+                            }).collect::<Vec<_>>()}
+                        </select>
+                    </label>
+                </div>
+                <div class="game__toolbar">
+                    <a href="#" on:click=move |ev| { ev.prevent_default(); set_show_help.set(true); }>{l10n("link.keys")}</a>
+                    {if daily {
+                        view! { <A href="/">{l10n("link.free_play")}</A> }
+                    } else {
+                        view! { <A href="/daily">{l10n("link.daily")}</A> }
                     }}
-                    {move || if direction_hint.get().len() > 0 {
-                        view! {
-                            <p style="text-align: center; color: #1d4ed8;font-size: 20px">{move || direction_hint.get()}{" "}{move || match direction_hint.get().as_str(){
-                                "N" => "⬆️",
-                                "S" => "⬇️",
-                                "E" => "➡️",
-                                "W" => "⬅️",
-                                "NE" => "↗️",
-                                "NW" => "↖️",
-                                "SE" => "↘️",
-                                "SW" => "↙️",
-                                _ => "😱"
-                            }}</p>
-                        }
+                    {if online {
+                        view! { <A href="/">{l10n("link.offline")}</A> }
                     } else {
-                        view! {
-                            <p style="text-align: center; color: white; user-select: none;font-size: 20px">{"a"}</p>
-                        }
+                        view! { <A href="/online">{l10n("link.online")}</A> }
                     }}
+                    {(!ranked_daily).then(|| view! { <A href="/online/daily">{l10n("link.ranked_daily")}</A> })}
+                    <A href="/stats">{l10n("link.stats")}</A>
+                    <A href="/leaderboard">{l10n("link.leaderboard")}</A>
+                    <A href="/tutorial">{l10n("link.tutorial")}</A>
                 </div>
-                <div style="display: grid; grid-template-columns: repeat(12, 1fr); grid-template-rows: repeat(9, 1fr); width: 100%; height: 100%;margin: auto;">
-                    {cells}
-                </div>
-                <button on:click=handle_peep style="padding: 10px; padding-left: 20px; padding-right: 20px; margin-top: 20px; background-color: green; color: white; border-radius: 4px; border: none; width: 200px; font-size: 20px;cursor:pointer">
-                    {move || button_text.get()}
+            </div>
+        </div>
+    }
+}
+
+// Only renders the cells inside the scrolled viewport, so that very large boards don't create
+// hundreds of thousands of DOM nodes.
+#[component]
+fn VirtualBoard<F, V>(board: BoardSignals, cell_view: F) -> impl IntoView
+where
+    F: Fn(CellSignals, String) -> V + Copy + 'static,
+    V: IntoView,
+{
+    let container = create_node_ref::<Div>();
+    let locale = use_locale();
+    let (viewport, set_viewport) = create_signal((0, 0, 800, 800));
+
+    let measure = move || {
+        if let Some(element) = container.get_untracked() {
+            set_viewport.set((
+                element.scroll_top(),
+                element.scroll_left(),
+                element.client_height(),
+                element.client_width(),
+            ));
+        }
+    };
+    create_effect(move |_| {
+        if container.get().is_some() {
+            measure();
+        }
+    });
+
+    // The headers stay along the top and left edges of the viewport as it scrolls
+    let visible_rows = move || {
+        let (top, left, height, width) = viewport.get();
+        let first_row = top / VIRTUAL_CELL_SIZE;
+        let first_column = left / VIRTUAL_CELL_SIZE;
+        let last_row = (top + height) / VIRTUAL_CELL_SIZE + 1;
+        let last_column = (left + width) / VIRTUAL_CELL_SIZE + 1;
+        let last_row = last_row.min(board.rows.get());
+        let last_column = last_column.min(board.columns.get());
+        let header = view! {
+            <div role="row" aria-rowindex=1 class="board__row">
+                <div
+                    class="virtual-board__header virtual-board__header--corner"
+                    style=format!("top: {}px; left: {}px; width: {}px; height: {}px;", top, left, VIRTUAL_HEADER_SIZE, VIRTUAL_HEADER_SIZE)
+                ></div>
+                {(first_column..last_column).map(|y| view! {
+                    <div
+                        role="columnheader"
+                        aria-colindex=y + 2
+                        class="virtual-board__header"
+                        style=format!(
+                            "top: {}px; left: {}px; width: {}px; height: {}px;",
+                            top,
+                            VIRTUAL_HEADER_SIZE + y * VIRTUAL_CELL_SIZE,
+                            VIRTUAL_CELL_SIZE,
+                            VIRTUAL_HEADER_SIZE
+                        )
+                    >
+                        {column_label(y)}
+                    </div>
+                }).collect::<Vec<_>>()}
+            </div>
+        };
+        let rows = (first_row..last_row)
+            .map(|x| {
+                let cells = (first_column..last_column)
+                    .map(|y| {
+                        let position = format!(
+                            "top: {}px; left: {}px; width: {}px; height: {}px;",
+                            VIRTUAL_HEADER_SIZE + x * VIRTUAL_CELL_SIZE,
+                            VIRTUAL_HEADER_SIZE + y * VIRTUAL_CELL_SIZE,
+                            VIRTUAL_CELL_SIZE,
+                            VIRTUAL_CELL_SIZE
+                        );
+                        cell_view(board.cell(x, y), position)
+                    })
+                    .collect::<Vec<_>>();
+                view! {
+                    <div role="row" aria-rowindex=x + 2 class="board__row">
+                        <div
+                            role="rowheader"
+                            aria-colindex=1
+                            class="virtual-board__header"
+                            style=format!(
+                                "top: {}px; left: {}px; width: {}px; height: {}px;",
+                                VIRTUAL_HEADER_SIZE + x * VIRTUAL_CELL_SIZE,
+                                left,
+                                VIRTUAL_HEADER_SIZE,
+                                VIRTUAL_CELL_SIZE
+                            )
+                        >
+                            {x + 1}
+                        </div>
+                        {cells}
+                    </div>
+                }
+            })
+            .collect::<Vec<_>>();
+        (header, rows)
+    };
+
+    view! {
+        <div
+            node_ref=container
+            on:scroll=move |_| measure()
+            role="grid"
+            aria-label=move || t(locale.get(), "board.label")
+            aria-rowcount=move || board.rows.get() + 1
+            aria-colcount=move || board.columns.get() + 1
+            class="virtual-board"
+            dir="ltr"
+        >
+            <div class="virtual-board__content" style=move || format!(
+                "width: {}px; height: {}px;",
+                VIRTUAL_HEADER_SIZE + board.columns.get() * VIRTUAL_CELL_SIZE,
+                VIRTUAL_HEADER_SIZE + board.rows.get() * VIRTUAL_CELL_SIZE
+            )>
+                {visible_rows}
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn SettingsDialog<S, C>(initial: GameSettings, on_start: S, on_cancel: C) -> impl IntoView
+where
+    S: Fn(GameSettings) + Copy + 'static,
+    C: Fn() + Copy + 'static,
+{
+    let (settings, set_settings) = create_signal(initial);
+    let (error, set_error) = create_signal(None::<SettingsError>);
+    let locale = use_locale();
+
+    let number_input = move |label: &'static str, get: fn(&GameSettings) -> i32, set: fn(&mut GameSettings, i32)| {
+        view! {
+            <label class="field">
+                {move || t(locale.get(), label)}
+                <input
+                    type="number"
+                    prop:value=move || settings.with(get)
+                    on:input=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<i32>() {
+                            set_settings.update(|settings| {
+                                set(settings, value);
+                                settings.preset = None;
+                            });
+                        }
+                    }
+                />
+            </label>
+        }
+    };
+
+    let handle_start = move |_| {
+        let settings = settings.get();
+        match settings.validate() {
+            Ok(()) => on_start(settings),
+            Err(err) => set_error.set(Some(err)),
+        }
+    };
+
+    view! {
+        <div class="overlay">
+            <div class="dialog dialog--form">
+                <h1>{move || t(locale.get(), "settings.title")}</h1>
+                <label class="field">
+                    {move || t(locale.get(), "settings.difficulty")}
+                    <select
+                        on:change=move |ev| {
+                            match event_target_value(&ev).parse::<Preset>() {
+                                Ok(preset) => set_settings.set(preset.settings()),
+                                Err(_) => set_settings.update(|settings| settings.preset = None),
+                            }
+                        }
+                    >
+                        {Preset::ALL.into_iter().map(|preset| view! {
+                            <option value=preset.name() prop:selected=move || settings.with(|s| s.preset == Some(preset))>
+                                {move || t(locale.get(), &format!("preset.{}", preset.name()))}
+                            </option>
+                        }).collect::<Vec<_>>()}
+                        <option value="custom" prop:selected=move || settings.with(|s| s.preset.is_none())>
+                            {move || t(locale.get(), "preset.custom")}
+                        </option>
+                    </select>
+                </label>
+                {number_input("settings.width", |s| s.width, |s, v| s.width = v)}
+                {number_input("settings.height", |s| s.height, |s, v| s.height = v)}
+                {number_input("settings.attempts", |s| s.score, |s, v| s.score = v)}
+                {number_input("settings.busts", |s| s.busts, |s, v| s.busts = v)}
+                <p class="error">{move || error.with(|error| error.as_ref().map(|error| error.message(locale.get())))}</p>
+                <button on:click=handle_start class="button button--success">
+                    {move || t(locale.get(), "button.start")}
                 </button>
-                <button on:click=handle_bust style="padding: 10px; padding-left: 20px; padding-right: 20px; margin-top: 20px; background-color: red; color: white; border-radius: 4px; border: none; width: 200px; font-size: 20px;cursor: pointer">
-                    Bust {"("}{move || clicked_cell.get().0}, {move || clicked_cell.get().1}{")"}
+                <button on:click=move |_| on_cancel() class="button">
+                    {move || t(locale.get(), "button.cancel")}
                 </button>
             </div>
         </div>
@@ -0,0 +1,552 @@
+// Ways of keeping the probability of the ghost being in each cell. `ExactGrid` updates every cell
+// on every reading and is the filter `Game` runs, `ParticleFilter` tracks a fixed number of guesses
+// instead so the cost of a reading doesn't grow with the board.
+use crate::game::{sensor_row, BeliefUpdate, CellFactors, Direction, GameSettings, SensorColor, DIRECTIONS};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PARTICLES: usize = 10_000;
+
+// The probability of the ghost being in each `bin` x `bin` block of cells, row by row. The blocks
+// of the last row and column are cut short by the edges of the board.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub rows: i32,
+    pub columns: i32,
+    pub bin: i32,
+    pub values: Vec<f32>,
+}
+
+impl Heatmap {
+    fn new(settings: &GameSettings, bin: i32) -> Heatmap {
+        let bin = bin.max(1);
+        let rows = (settings.height + bin - 1) / bin;
+        let columns = (settings.width + bin - 1) / bin;
+        Heatmap {
+            rows,
+            columns,
+            bin,
+            values: vec![0.0; (rows * columns) as usize],
+        }
+    }
+
+    fn add(&mut self, x: i32, y: i32, probability: f32) {
+        self.values[((x / self.bin) * self.columns + y / self.bin) as usize] += probability;
+    }
+
+    // The probability of the block holding the cell (x, y)
+    pub fn get(&self, x: i32, y: i32) -> f32 {
+        self.values[((x / self.bin) * self.columns + y / self.bin) as usize]
+    }
+
+    // The smallest blocks that cut the board into at most `blocks` of them
+    pub fn bin_for(settings: &GameSettings, blocks: i32) -> i32 {
+        (1..)
+            .find(|bin| ((settings.height + bin - 1) / bin) * ((settings.width + bin - 1) / bin) <= blocks.max(1))
+            .unwrap_or(1)
+    }
+}
+
+pub trait InferenceEngine {
+    fn name(&self) -> String;
+    // Forgets every reading, the ghost may be anywhere
+    fn reset(&mut self);
+    // Records a reading of `color` and `direction` on the cell (x, y)
+    fn observe(&mut self, x: i32, y: i32, color: &str, direction: &str);
+    // Records a missed bust of the cell (x, y): the ghost can't be there
+    fn rule_out(&mut self, x: i32, y: i32);
+    fn probability(&self, x: i32, y: i32) -> f32;
+    fn heatmap(&self, bin: i32) -> Heatmap;
+}
+
+// The likelihood of a reading only depends on the distance between a cell and the sensed one,
+// through the row of the sensor table, and on which side of the sensed cell the cell is. Both
+// tables are worked out once per reading so the loops over the cells only look them up.
+#[derive(Clone, Debug)]
+struct ReadingFactors {
+    // The sensor row of each distance
+    rows: Vec<usize>,
+    by_distance: Vec<f64>,
+    // By `side_index`
+    by_side: [f64; 9],
+}
+
+// Where a cell `row` rows and `column` columns off the sensed one is in `ReadingFactors::by_side`
+fn side_index(row: i32, column: i32) -> usize {
+    ((row.signum() + 1) * 3 + column.signum() + 1) as usize
+}
+
+impl ReadingFactors {
+    // Anything that isn't green, yellow or orange reads as red
+    fn new(settings: &GameSettings, color: &str, direction: &str) -> ReadingFactors {
+        let color = SensorColor::from_name(color).unwrap_or(SensorColor::Red);
+        ReadingFactors::of(settings, color, Direction::from_name(direction))
+    }
+
+    fn of(settings: &GameSettings, color: SensorColor, direction: Option<Direction>) -> ReadingFactors {
+        let table = &settings.conditional_probabilities;
+        let rows = (0..settings.height + settings.width - 1)
+            .map(|distance| sensor_row(table, distance))
+            .collect::<Vec<_>>();
+        let by_distance = rows.iter().map(|row| table[*row].likelihood(color)).collect();
+        let reliability = settings.direction_reliability as f64;
+        let mut by_side = [0.0; 9];
+        for (side, likelihood) in by_side.iter_mut().enumerate() {
+            let side = Direction::from_sides(side as i32 / 3 - 1, side as i32 % 3 - 1);
+            *likelihood = match Some(side) == direction {
+                true => reliability,
+                false => (1.0 - reliability) / (DIRECTIONS.len() - 1) as f64,
+            };
+        }
+        ReadingFactors { rows, by_distance, by_side }
+    }
+
+    // With the ghost `row` rows and `column` columns off the sensed cell
+    fn likelihood(&self, row: i32, column: i32) -> f64 {
+        self.by_distance[(row.abs() + column.abs()) as usize] * self.by_side[side_index(row, column)]
+    }
+}
+
+// The filter `Game` runs, on a flat belief
+#[derive(Clone, Debug)]
+pub struct ExactGrid {
+    settings: GameSettings,
+    // Row by row, like `Game::cells`
+    pub probabilities: Vec<f64>,
+}
+
+impl ExactGrid {
+    pub fn new(settings: &GameSettings) -> ExactGrid {
+        let mut engine = ExactGrid {
+            settings: settings.clone(),
+            probabilities: vec![],
+        };
+        engine.reset();
+        engine
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (x * self.settings.width + y) as usize
+    }
+
+    // What a reading of `color` and `direction` on the cell (x, y) would do to every cell, without
+    // doing it. The posteriors are the ones `observe` computes.
+    // Shares the probability of each block of `heatmap` evenly between its cells
+    pub fn spread(&mut self, heatmap: &Heatmap) {
+        let (height, width, bin) = (self.settings.height, self.settings.width, heatmap.bin);
+        for x in 0..height {
+            let rows = bin.min(height - x / bin * bin);
+            for y in 0..width {
+                let columns = bin.min(width - y / bin * bin);
+                let index = self.index(x, y);
+                self.probabilities[index] = heatmap.get(x, y) as f64 / (rows * columns) as f64;
+            }
+        }
+    }
+
+    pub fn explain(&self, x: i32, y: i32, color: SensorColor, direction: Option<Direction>) -> BeliefUpdate {
+        let factors = ReadingFactors::of(&self.settings, color, direction);
+        let columns = self.settings.width;
+        let mut cells = self
+            .probabilities
+            .iter()
+            .enumerate()
+            .map(|(i, prior)| {
+                let (row, column) = (i as i32 / columns - x, i as i32 % columns - y);
+                let distance = (row.abs() + column.abs()) as usize;
+                CellFactors {
+                    prior: *prior,
+                    row: factors.rows[distance],
+                    color: factors.by_distance[distance],
+                    direction: factors.by_side[side_index(row, column)],
+                    posterior: 0.0,
+                }
+            })
+            .collect::<Vec<_>>();
+        let normalisation = cells.iter().map(CellFactors::unnormalised).sum::<f64>();
+        for cell in &mut cells {
+            cell.posterior = cell.unnormalised() / normalisation;
+        }
+        BeliefUpdate {
+            x,
+            y,
+            color,
+            direction,
+            cells,
+            normalisation,
+        }
+    }
+}
+
+impl InferenceEngine for ExactGrid {
+    fn name(&self) -> String {
+        "exact".to_string()
+    }
+
+    fn reset(&mut self) {
+        let cells = (self.settings.width * self.settings.height) as usize;
+        self.probabilities = vec![1.0 / cells as f64; cells];
+    }
+
+    fn observe(&mut self, x: i32, y: i32, color: &str, direction: &str) {
+        let ReadingFactors { by_distance, by_side, .. } = ReadingFactors::new(&self.settings, color, direction);
+        // The distance and the side of each column, the same on every row
+        let columns = (0..self.settings.width)
+            .map(|j| ((j - y).unsigned_abs() as usize, ((j - y).signum() + 1) as usize))
+            .collect::<Vec<_>>();
+
+        let mut sum = 0.0;
+        for (i, row) in self.probabilities.chunks_exact_mut(columns.len()).enumerate() {
+            let row_distance = (i as i32 - x).unsigned_abs() as usize;
+            let row_sides = &by_side[((i as i32 - x).signum() + 1) as usize * 3..][..3];
+            for (probability, (column_distance, column_side)) in row.iter_mut().zip(&columns) {
+                *probability *= by_distance[row_distance + column_distance] * row_sides[*column_side];
+                sum += *probability;
+            }
+        }
+        for probability in &mut self.probabilities {
+            *probability /= sum;
+        }
+    }
+
+    fn rule_out(&mut self, x: i32, y: i32) {
+        let index = self.index(x, y);
+        let missed = self.probabilities[index];
+        if missed >= 1.0 {
+            return;
+        }
+        for probability in &mut self.probabilities {
+            *probability /= 1.0 - missed;
+        }
+        self.probabilities[index] = 0.0;
+    }
+
+    fn probability(&self, x: i32, y: i32) -> f32 {
+        self.probabilities[self.index(x, y)] as f32
+    }
+
+    fn heatmap(&self, bin: i32) -> Heatmap {
+        let mut heatmap = Heatmap::new(&self.settings, bin);
+        let columns = self.settings.width;
+        for (i, p) in self.probabilities.iter().enumerate() {
+            heatmap.add(i as i32 / columns, i as i32 % columns, *p as f32);
+        }
+        heatmap
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Reading {
+    x: i32,
+    y: i32,
+    factors: ReadingFactors,
+}
+
+// Weighs `particles` guesses of the ghost position by the readings. Once the weights pile up on a
+// few particles (an effective sample size under half the particles) they are drawn again by
+// systematic resampling, then each moves to a neighbouring cell when the readings so far explain
+// it as well, so the copies spread out again instead of sitting on the same cells for good.
+#[derive(Clone, Debug)]
+pub struct ParticleFilter {
+    settings: GameSettings,
+    pub particles: usize,
+    positions: Vec<(i32, i32)>,
+    weights: Vec<f32>,
+    readings: Vec<Reading>,
+    ruled_out: Vec<(i32, i32)>,
+    rng: ChaCha8Rng,
+}
+
+impl ParticleFilter {
+    pub fn new(settings: &GameSettings, particles: usize, seed: u64) -> ParticleFilter {
+        let mut engine = ParticleFilter {
+            settings: settings.clone(),
+            particles: particles.max(1),
+            positions: vec![],
+            weights: vec![],
+            readings: vec![],
+            ruled_out: vec![],
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        engine.reset();
+        engine
+    }
+
+    pub fn effective_sample_size(&self) -> f32 {
+        1.0 / self.weights.iter().map(|w| w * w).sum::<f32>()
+    }
+
+    fn random_position(&mut self) -> (i32, i32) {
+        (
+            self.rng.gen_range(0..self.settings.height),
+            self.rng.gen_range(0..self.settings.width),
+        )
+    }
+
+    // How well every reading so far explains the ghost being on `position`, as a log so that long
+    // histories don't round to zero
+    fn history_log_likelihood(&self, position: (i32, i32)) -> f64 {
+        if self.ruled_out.contains(&position) {
+            return f64::NEG_INFINITY;
+        }
+        self.readings
+            .iter()
+            .map(|reading| (self.likelihood(reading, position) as f64).ln())
+            .sum()
+    }
+
+    fn likelihood(&self, reading: &Reading, position: (i32, i32)) -> f32 {
+        reading.factors.likelihood(position.0 - reading.x, position.1 - reading.y) as f32
+    }
+
+    // Returns false when every weight is zero
+    fn normalize(&mut self) -> bool {
+        let sum = self.weights.iter().sum::<f32>();
+        if sum <= 0.0 || !sum.is_finite() {
+            return false;
+        }
+        self.weights.iter_mut().for_each(|w| *w /= sum);
+        true
+    }
+
+    // Draws every particle again from the prior, weighed by the whole history. Used when the
+    // particles all missed the ghost.
+    fn redraw(&mut self) {
+        self.positions = (0..self.particles).map(|_| self.random_position()).collect();
+        let logs = self.positions.iter().map(|p| self.history_log_likelihood(*p)).collect::<Vec<_>>();
+        let best = logs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        self.weights = logs.iter().map(|log| (log - best).exp() as f32).collect();
+        if !self.normalize() {
+            self.weights = vec![1.0 / self.particles as f32; self.particles];
+        }
+    }
+
+    fn resample(&mut self) {
+        let n = self.particles;
+        let start = self.rng.gen::<f32>() / n as f32;
+        let mut positions = Vec::with_capacity(n);
+        let mut cumulative = self.weights[0];
+        let mut i = 0;
+        for k in 0..n {
+            let target = start + k as f32 / n as f32;
+            while cumulative < target && i + 1 < n {
+                i += 1;
+                cumulative += self.weights[i];
+            }
+            positions.push(self.positions[i]);
+        }
+        self.positions = positions;
+        self.weights = vec![1.0 / n as f32; n];
+        self.rejuvenate();
+    }
+
+    // One Metropolis step per particle towards a random neighbouring cell
+    fn rejuvenate(&mut self) {
+        const MOVES: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        let mut cache = std::collections::HashMap::new();
+        for k in 0..self.positions.len() {
+            let (x, y) = self.positions[k];
+            let (dx, dy) = MOVES[self.rng.gen_range(0..MOVES.len())];
+            let proposal = (x + dx, y + dy);
+            if !(0..self.settings.height).contains(&proposal.0) || !(0..self.settings.width).contains(&proposal.1) {
+                continue;
+            }
+            let current = *cache.entry((x, y)).or_insert_with(|| self.history_log_likelihood((x, y)));
+            let proposed = *cache.entry(proposal).or_insert_with(|| self.history_log_likelihood(proposal));
+            if proposed > f64::NEG_INFINITY && self.rng.gen::<f64>().ln() < proposed - current {
+                self.positions[k] = proposal;
+            }
+        }
+    }
+
+    fn settle(&mut self) {
+        if !self.normalize() {
+            self.redraw();
+        }
+        if self.effective_sample_size() < self.particles as f32 / 2.0 {
+            self.resample();
+        }
+    }
+}
+
+impl InferenceEngine for ParticleFilter {
+    fn name(&self) -> String {
+        format!("particles-{}", self.particles)
+    }
+
+    fn reset(&mut self) {
+        self.readings.clear();
+        self.ruled_out.clear();
+        self.positions = (0..self.particles).map(|_| self.random_position()).collect();
+        self.weights = vec![1.0 / self.particles as f32; self.particles];
+    }
+
+    fn observe(&mut self, x: i32, y: i32, color: &str, direction: &str) {
+        let reading = Reading {
+            x,
+            y,
+            factors: ReadingFactors::new(&self.settings, color, direction),
+        };
+        for (weight, position) in self.weights.iter_mut().zip(&self.positions) {
+            *weight *= reading.factors.likelihood(position.0 - x, position.1 - y) as f32;
+        }
+        self.readings.push(reading);
+        self.settle();
+    }
+
+    fn rule_out(&mut self, x: i32, y: i32) {
+        self.ruled_out.push((x, y));
+        for (weight, position) in self.weights.iter_mut().zip(&self.positions) {
+            if *position == (x, y) {
+                *weight = 0.0;
+            }
+        }
+        self.settle();
+    }
+
+    fn probability(&self, x: i32, y: i32) -> f32 {
+        self.positions
+            .iter()
+            .zip(&self.weights)
+            .filter(|(position, _)| **position == (x, y))
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    fn heatmap(&self, bin: i32) -> Heatmap {
+        let mut heatmap = Heatmap::new(&self.settings, bin);
+        for ((x, y), weight) in self.positions.iter().zip(&self.weights) {
+            heatmap.add(*x, *y, *weight);
+        }
+        heatmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::preset::Preset;
+
+    #[test]
+    fn explain_gives_the_posteriors_observe_computes() {
+        let mut engine = ExactGrid::new(&Preset::Hard.settings());
+        engine.observe(3, 4, "yellow", "SE");
+        engine.rule_out(5, 5);
+        let update = engine.explain(7, 2, SensorColor::Orange, Some(Direction::N));
+        engine.observe(7, 2, "orange", "N");
+        for (factors, probability) in update.cells.iter().zip(&engine.probabilities) {
+            assert_eq!(factors.posterior, *probability);
+        }
+        assert!((engine.probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exact_grid_posterior_is_the_normalised_likelihood() {
+        let settings = Preset::Normal.settings();
+        let reading = ReadingFactors::new(&settings, "orange", "NE");
+        let mut engine = ExactGrid::new(&settings);
+        engine.observe(4, 4, "orange", "NE");
+        // with a uniform prior the posterior of a cell is its likelihood over the sum of them
+        let sum = (0..settings.height)
+            .flat_map(|x| (0..settings.width).map(move |y| (x, y)))
+            .map(|(x, y)| reading.likelihood(x - 4, y - 4))
+            .sum::<f64>();
+        for (x, y) in [(2, 6), (4, 4), (8, 0), (4, 11)] {
+            let expected = reading.likelihood(x - 4, y - 4) / sum;
+            assert!((engine.probabilities[engine.index(x, y)] - expected).abs() < 1e-12);
+        }
+    }
+
+    // Readings drawn from a seeded board, fed to the exact grid and a particle filter
+    fn filters_after_readings(readings: &[(i32, i32)]) -> (Game, ExactGrid, ParticleFilter) {
+        let settings = Preset::Normal.settings();
+        let mut game = Game::from_seed(settings.clone(), 11);
+        game.place_ghost();
+        let mut exact = ExactGrid::new(&settings);
+        let mut particles = ParticleFilter::new(&settings, 20_000, 3);
+        for (x, y) in readings {
+            let (color, direction) = game.distance_sense(*x, *y).unwrap();
+            exact.observe(*x, *y, &color, &direction);
+            particles.observe(*x, *y, &color, &direction);
+        }
+        (game, exact, particles)
+    }
+
+    #[test]
+    fn particle_heatmap_comes_close_to_the_exact_posterior() {
+        let (_, exact, particles) = filters_after_readings(&[(0, 0), (8, 11), (4, 6), (2, 9)]);
+        let (exact, particles) = (exact.heatmap(3), particles.heatmap(3));
+        assert_eq!((particles.rows, particles.columns), (exact.rows, exact.columns));
+        // half the summed differences, the largest gap between the two in the mass of any set of blocks
+        let distance = exact.values.iter().zip(&particles.values).map(|(a, b)| (a - b).abs()).sum::<f32>() / 2.0;
+        assert!(distance < 0.05, "{} between {:?} and {:?}", distance, exact.values, particles.values);
+    }
+
+    #[test]
+    fn rule_out_takes_every_particle_off_the_cell() {
+        let (_, _, mut particles) = filters_after_readings(&[(0, 0), (4, 6)]);
+        let (x, y) = particles
+            .positions
+            .iter()
+            .zip(&particles.weights)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(position, _)| *position)
+            .unwrap();
+        assert!(particles.probability(x, y) > 0.0);
+        particles.rule_out(x, y);
+        assert_eq!(particles.probability(x, y), 0.0);
+        assert_eq!(particles.heatmap(1).get(x, y), 0.0);
+        assert!((particles.weights.iter().sum::<f32>() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn resampling_leaves_equal_weights_that_sum_to_one() {
+        let (_, _, mut particles) = filters_after_readings(&[(0, 0)]);
+        particles.observe(4, 6, "red", "N");
+        particles.resample();
+        assert_eq!(particles.positions.len(), particles.particles);
+        assert!(particles.weights.iter().all(|w| *w == 1.0 / particles.particles as f32));
+        assert!((particles.weights.iter().sum::<f32>() - 1.0).abs() < 1e-3);
+        assert!(particles.effective_sample_size() > particles.particles as f32 * 0.99);
+    }
+
+    #[test]
+    fn heatmap_blocks_add_up_their_cells() {
+        let (_, exact, _) = filters_after_readings(&[(3, 3)]);
+        // 9x12 cells in blocks of 5, the last row and column of blocks are cut short
+        let heatmap = exact.heatmap(5);
+        assert_eq!((heatmap.rows, heatmap.columns), (2, 3));
+        let block = (5..9)
+            .flat_map(|x| (10..12).map(move |y| (x, y)))
+            .map(|(x, y)| exact.probability(x, y))
+            .sum::<f32>();
+        assert!((heatmap.get(8, 11) - block).abs() < 1e-6);
+        assert!((heatmap.values.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn spreading_a_heatmap_keeps_the_mass_of_every_block() {
+        let (_, exact, _) = filters_after_readings(&[(3, 3), (7, 10)]);
+        let heatmap = exact.heatmap(4);
+        let mut spread = ExactGrid::new(&Preset::Normal.settings());
+        spread.spread(&heatmap);
+        assert_eq!(spread.heatmap(4).values.len(), heatmap.values.len());
+        for (a, b) in spread.heatmap(4).values.iter().zip(&heatmap.values) {
+            assert!((a - b).abs() < 1e-6);
+        }
+        // the last block of the 9x12 board has 1x4 cells
+        assert!((spread.probability(8, 11) - heatmap.get(8, 11) / 4.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn bins_cut_the_board_into_at_most_the_blocks_asked_for() {
+        let normal = Preset::Normal.settings();
+        assert_eq!(Heatmap::bin_for(&normal, 108), 1);
+        assert_eq!(Heatmap::bin_for(&normal, 107), 2);
+        assert_eq!(Heatmap::bin_for(&normal, 12), 3);
+        let large = GameSettings { width: 1000, height: 1000, ..normal };
+        assert_eq!(Heatmap::bin_for(&large, 40_000), 5);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::daily;
+use crate::game::{Action, Game, Status};
+use crate::preset::Preset;
+use leptos::server_fn::codec::Json;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+const MAX_NAME_LENGTH: usize = 20;
+
+// The rankings a game can go into: one per preset, and one per daily challenge
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "board", rename_all = "lowercase")]
+pub enum Board {
+    Preset { preset: Preset },
+    Daily { date: String },
+}
+
+// An online game sent for ranking. The board and the seed are read from the server's own copy of
+// the game, which the client never gets to touch, and the actions are played again on that seed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Submission {
+    pub name: String,
+    // The id `new_online_game` or `new_daily_game` gave the game
+    pub id: String,
+    // Every action of the game, in the order they were played
+    pub actions: Vec<Action>,
+}
+
+// A result as the server ranks it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RankedGame {
+    pub name: String,
+    pub seed: u64,
+    pub won: bool,
+    pub attempts_used: i32,
+    pub busts_used: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub attempts_used: i32,
+    pub busts_used: i32,
+    // Milliseconds since the Unix epoch
+    pub submitted_at: u64,
+}
+
+// The board a server game is ranked on. `daily` is the date the server created it as the day's
+// challenge on, which only ranks on that day's board while the server's own date is still `today`.
+// Other games are only ranked with the exact settings of a preset, so that everyone on a board
+// played by the same rules.
+pub fn board(game: &Game, daily: Option<&str>, today: &str) -> Result<Board, String> {
+    if let Some(date) = daily {
+        if date != today || game.settings != daily::settings() {
+            return Err(format!("The daily challenge of {} can't be ranked any more", date));
+        }
+        return Ok(Board::Daily { date: date.to_string() });
+    }
+    match game.settings.preset {
+        Some(preset) if game.settings == preset.settings() => Ok(Board::Preset { preset }),
+        _ => Err("Only games played on a difficulty preset are ranked".to_string()),
+    }
+}
+
+// Plays `actions` again on a fresh game from the seed of the server's copy of the game. They have to
+// end it the way the server's copy ended, with as many attempts and busts left.
+pub fn replay(game: &Game, actions: &[Action]) -> Result<Game, String> {
+    let mut replayed = Game::from_seed(game.settings.clone(), game.seed);
+    // the result doesn't depend on the posterior
+    replayed.update_belief = false;
+    replayed.place_ghost();
+    replayed.compute_initial_prior_probabilities();
+    for action in actions {
+        replayed.apply(*action).map_err(|err| format!("The actions don't replay: {}", err))?;
+    }
+    if (replayed.status, replayed.score, replayed.busts) != (game.status, game.score, game.busts) {
+        return Err("The actions don't replay to the result of the game".to_string());
+    }
+    Ok(replayed)
+}
+
+// The board and the result of a finished server game, once its actions replayed to it
+pub fn verify(
+    name: &str,
+    game: &Game,
+    actions: &[Action],
+    daily: Option<&str>,
+    today: &str,
+) -> Result<(Board, RankedGame), String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("Names are 1 to {} characters long", MAX_NAME_LENGTH));
+    }
+    if game.status == Status::Playing {
+        return Err("The game isn't finished".to_string());
+    }
+    let game = &replay(game, actions)?;
+    let board = board(game, daily, today)?;
+    let ranked = RankedGame {
+        name: name.to_string(),
+        seed: game.seed,
+        won: game.status == Status::Won,
+        attempts_used: game.settings.score - game.score,
+        busts_used: game.settings.busts - game.busts,
+    };
+    Ok((board, ranked))
+}
+
+// Ranks a finished online game, returns its place on its board if it was won. Each game is ranked
+// at most once, a game that couldn't be saved can be submitted again.
+#[server(prefix = "/api", endpoint = "submit_result", input = Json)]
+pub async fn submit_result(submission: Submission) -> Result<Option<usize>, ServerFnError> {
+    let today = daily::server_today();
+    crate::game_store::submit(&submission.id, |game, daily| {
+        let (board, ranked) = verify(&submission.name, game, &submission.actions, daily, &today)?;
+        crate::leaderboard_db::insert(&board, &ranked)
+    })
+    .map_err(ServerFnError::new)
+}
+
+// The best won games of a board: fewest attempts, then fewest busts, then first submitted
+#[server(prefix = "/api", endpoint = "top_results", input = Json)]
+pub async fn top_results(board: Board) -> Result<Vec<LeaderboardEntry>, ServerFnError> {
+    crate::leaderboard_db::top(&board, 50).map_err(ServerFnError::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, GameSettings};
+
+    fn won_game(settings: GameSettings) -> (Game, Vec<Action>) {
+        let mut game = Game::from_seed(settings, 11);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        let (x, y) = game.ghost_position;
+        let actions = vec![Action::Sense { x: 0, y: 0 }, Action::Bust { x, y }];
+        for action in &actions {
+            game.apply(*action).unwrap();
+        }
+        (game, actions)
+    }
+
+    #[test]
+    fn finished_preset_game_is_ranked_with_the_servers_result() {
+        let (game, actions) = won_game(Preset::Hard.settings());
+        let (board, ranked) = verify(" Ada ", &game, &actions, None, "2024-05-01").unwrap();
+        assert_eq!(board, Board::Preset { preset: Preset::Hard });
+        let expected = RankedGame {
+            name: "Ada".to_string(),
+            seed: 11,
+            won: true,
+            attempts_used: 1,
+            busts_used: 1,
+        };
+        assert_eq!(ranked, expected);
+    }
+
+    #[test]
+    fn unfinished_game_is_rejected() {
+        let mut game = Game::from_seed(Preset::Normal.settings(), 11);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        assert!(verify("Ada", &game, &[], None, "2024-05-01").is_err());
+    }
+
+    #[test]
+    fn actions_that_dont_replay_to_the_result_are_rejected() {
+        let (game, actions) = won_game(Preset::Normal.settings());
+        // the bust alone wins with an attempt less than the game used
+        assert!(verify("Ada", &game, &actions[1..], None, "2024-05-01").is_err());
+        // a missed bust instead of the sensing
+        let (x, y) = game.ghost_position;
+        let missed = [Action::Bust { x: (x + 1) % game.rows(), y }, actions[1]];
+        assert!(verify("Ada", &game, &missed, None, "2024-05-01").is_err());
+        // actions after the end of the game
+        let extra = [actions.clone(), actions.clone()].concat();
+        assert!(verify("Ada", &game, &extra, None, "2024-05-01").is_err());
+        assert!(verify("Ada", &game, &actions, None, "2024-05-01").is_ok());
+    }
+
+    #[test]
+    fn game_off_the_preset_settings_is_rejected() {
+        let settings = GameSettings {
+            width: 5,
+            ..Preset::Normal.settings()
+        };
+        let (game, actions) = won_game(settings);
+        assert!(verify("Ada", &game, &actions, None, "2024-05-01").is_err());
+    }
+
+    #[test]
+    fn daily_game_only_ranks_on_the_servers_day() {
+        let (game, actions) = won_game(daily::settings());
+        let (board, _) = verify("Ada", &game, &actions, Some("2024-05-01"), "2024-05-01").unwrap();
+        assert_eq!(board, Board::Daily { date: "2024-05-01".to_string() });
+        assert!(verify("Ada", &game, &actions, Some("2024-04-30"), "2024-05-01").is_err());
+    }
+
+    #[test]
+    fn names_must_be_1_to_20_characters() {
+        let (game, actions) = won_game(Preset::Normal.settings());
+        assert!(verify("  ", &game, &actions, None, "2024-05-01").is_err());
+        assert!(verify(&"a".repeat(21), &game, &actions, None, "2024-05-01").is_err());
+        assert!(verify(&"é".repeat(20), &game, &actions, None, "2024-05-01").is_ok());
+    }
+}
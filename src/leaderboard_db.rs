@@ -0,0 +1,119 @@
+use crate::daily;
+use crate::leaderboard::{Board, LeaderboardEntry, RankedGame};
+use crate::preset::Preset;
+use rusqlite::{params, Connection};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS results (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    preset TEXT NOT NULL,
+    daily TEXT,
+    seed INTEGER NOT NULL,
+    won INTEGER NOT NULL,
+    attempts_used INTEGER NOT NULL,
+    busts_used INTEGER NOT NULL,
+    submitted_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS results_board ON results (preset, daily, won, attempts_used, busts_used);
+";
+
+// The database file is `leaderboard.db` in the working directory unless `LEADERBOARD_DB` says
+// otherwise
+fn open() -> Result<Connection, String> {
+    let path = std::env::var("LEADERBOARD_DB").unwrap_or_else(|_| "leaderboard.db".to_string());
+    let connection = Connection::open(&path).map_err(|err| format!("Could not open {}: {}", path, err))?;
+    connection
+        .execute_batch(SCHEMA)
+        .map_err(|err| format!("Could not set up {}: {}", path, err))?;
+    Ok(connection)
+}
+
+// Runs `f` on the database, opened the first time it is needed. A database that couldn't be opened
+// is tried again on the next call. Like the games in `game_store`, a poisoned lock is taken over:
+// each statement is saved whole or not at all, so a panic leaves nothing half written.
+fn with_connection<T>(f: impl FnOnce(&Connection) -> Result<T, String>) -> Result<T, String> {
+    static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+    let mut connection = CONNECTION.lock().unwrap_or_else(PoisonError::into_inner);
+    let connection = match &mut *connection {
+        Some(connection) => connection,
+        empty => empty.insert(open()?),
+    };
+    f(connection)
+}
+
+// The columns a board is stored as. Daily challenges are played on the normal preset.
+fn columns(board: &Board) -> (String, Option<&str>) {
+    match board {
+        Board::Preset { preset } => (preset.name().to_string(), None),
+        Board::Daily { date } => {
+            let preset = daily::settings().preset.unwrap_or(Preset::Normal);
+            (preset.name().to_string(), Some(date))
+        }
+    }
+}
+
+// Stores a verified game and returns its place on the board if it was won
+pub fn insert(board: &Board, ranked: &RankedGame) -> Result<Option<usize>, String> {
+    let (preset, daily) = columns(board);
+    let submitted_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+    with_connection(|connection| {
+        connection
+            .execute(
+                "INSERT INTO results (name, preset, daily, seed, won, attempts_used, busts_used, submitted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    ranked.name,
+                    preset,
+                    daily,
+                    ranked.seed as i64,
+                    ranked.won,
+                    ranked.attempts_used,
+                    ranked.busts_used,
+                    submitted_at
+                ],
+            )
+            .map_err(|err| format!("Could not save the result: {}", err))?;
+        if !ranked.won {
+            return Ok(None);
+        }
+        let id = connection.last_insert_rowid();
+        let ahead: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM results
+                 WHERE preset = ?1 AND daily IS ?2 AND won AND (attempts_used, busts_used, id) < (?3, ?4, ?5)",
+                params![preset, daily, ranked.attempts_used, ranked.busts_used, id],
+                |row| row.get(0),
+            )
+            .map_err(|err| format!("Could not rank the result: {}", err))?;
+        Ok(Some(ahead as usize + 1))
+    })
+}
+
+pub fn top(board: &Board, limit: usize) -> Result<Vec<LeaderboardEntry>, String> {
+    let (preset, daily) = columns(board);
+    with_connection(|connection| {
+        let mut statement = connection
+            .prepare(
+                "SELECT name, attempts_used, busts_used, submitted_at FROM results
+                 WHERE preset = ?1 AND daily IS ?2 AND won
+                 ORDER BY attempts_used, busts_used, id
+                 LIMIT ?3",
+            )
+            .map_err(|err| err.to_string())?;
+        let entries = statement
+            .query_map(params![preset, daily, limit as i64], |row| {
+                Ok(LeaderboardEntry {
+                    name: row.get(0)?,
+                    attempts_used: row.get(1)?,
+                    busts_used: row.get(2)?,
+                    submitted_at: row.get::<_, i64>(3)? as u64,
+                })
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| format!("Could not read the leaderboard: {}", err))?;
+        Ok(entries)
+    })
+}
@@ -0,0 +1,148 @@
+use crate::daily;
+use crate::i18n::{self, t, tr, use_locale};
+use crate::leaderboard::{self, Board, LeaderboardEntry, Submission};
+use crate::preset::Preset;
+use crate::stats_view::format_date;
+use leptos::*;
+use leptos_router::A;
+
+#[component]
+pub fn LeaderboardView() -> impl IntoView {
+    let (board, set_board) = create_signal(Board::Preset { preset: Preset::Normal });
+    let results = create_resource(move || board.get(), leaderboard::top_results);
+    let locale = use_locale();
+    let l10n = move |key: &'static str| move || t(locale.get(), key);
+
+    let handle_board = move |ev: ev::Event| {
+        let value = event_target_value(&ev);
+        match value.parse::<Preset>() {
+            Ok(preset) => set_board.set(Board::Preset { preset }),
+            Err(_) => set_board.set(Board::Daily { date: daily::today_utc() }),
+        }
+    };
+
+    let row = move |(i, entry): (usize, &LeaderboardEntry)| {
+        let locale = locale.get();
+        view! {
+            <tr>
+                <td>{i18n::number(locale, i + 1)}</td>
+                <td>{entry.name.clone()}</td>
+                <td>{i18n::number(locale, entry.attempts_used)}</td>
+                <td>{i18n::number(locale, entry.busts_used)}</td>
+                <td>{format_date(entry.submitted_at, locale)}</td>
+            </tr>
+        }
+    };
+
+    let table = move || {
+        results.get().map(|results| match results {
+            Ok(entries) if entries.is_empty() => view! { <p>{l10n("leaderboard.no_results")}</p> }.into_view(),
+            Ok(entries) => view! {
+                <table class="table">
+                    <thead>
+                        <tr>
+                            <th>{l10n("leaderboard.rank")}</th>
+                            <th>{l10n("leaderboard.name")}</th>
+                            <th>{l10n("leaderboard.attempts")}</th>
+                            <th>{l10n("leaderboard.busts")}</th>
+                            <th>{l10n("leaderboard.date")}</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {entries.iter().enumerate().map(row).collect::<Vec<_>>()}
+                    </tbody>
+                </table>
+            }.into_view(),
+            Err(err) => view! {
+                <p class="error">{tr(locale.get(), "leaderboard.load_failed", &[("error", err.to_string())])}</p>
+            }.into_view(),
+        })
+    };
+
+    view! {
+        <div class="page">
+            <h1>{l10n("leaderboard.title")}</h1>
+            <div class="page__row">
+                <select on:change=handle_board>
+                    {Preset::ALL.into_iter().map(|preset| view! {
+                        <option value=preset.name() prop:selected=move || board.get() == Board::Preset { preset }>
+                            {move || t(locale.get(), &format!("preset.{}", preset.name()))}
+                        </option>
+                    }).collect::<Vec<_>>()}
+                    <option value="daily" prop:selected=move || matches!(board.get(), Board::Daily { .. })>
+                        {l10n("leaderboard.daily")}
+                    </option>
+                </select>
+                {move || match board.get() {
+                    Board::Daily { date } => view! {
+                        <input
+                            type="date"
+                            prop:value=date
+                            on:change=move |ev| set_board.set(Board::Daily { date: event_target_value(&ev) })
+                        />
+                    }.into_view(),
+                    Board::Preset { .. } => view! {}.into_view(),
+                }}
+            </div>
+            <Transition fallback=move || view! { <p>{l10n("leaderboard.loading")}</p> }>
+                {table}
+            </Transition>
+            <A href="/">{l10n("link.back")}</A>
+        </div>
+    }
+}
+
+// Sends a won online game to the leaderboard under the name the player picks
+#[component]
+pub fn SubmitResult(submission: Submission) -> impl IntoView {
+    let (name, set_name) = create_signal(String::new());
+    let (message, set_message) = create_signal(None::<String>);
+    let (sent, set_sent) = create_signal(false);
+    let submission = store_value(submission);
+    let locale = use_locale();
+
+    let handle_submit = move |_| {
+        let submission = Submission {
+            name: name.get(),
+            ..submission.get_value()
+        };
+        spawn_local(async move {
+            match leaderboard::submit_result(submission).await {
+                Ok(rank) => {
+                    set_sent.set(true);
+                    let locale = locale.get_untracked();
+                    set_message.set(Some(match rank {
+                        Some(rank) => tr(locale, "leaderboard.ranked", &[("rank", i18n::number(locale, rank))]),
+                        None => t(locale, "leaderboard.saved"),
+                    }));
+                }
+                Err(err) => set_message.set(Some(tr(
+                    locale.get_untracked(),
+                    "leaderboard.submit_failed",
+                    &[("error", err.to_string())],
+                ))),
+            }
+        });
+    };
+
+    view! {
+        <div class="submit-result">
+            {move || (!sent.get()).then(|| view! {
+                <div class="submit-result__form">
+                    <input
+                        type="text"
+                        placeholder=move || t(locale.get(), "leaderboard.your_name")
+                        maxlength="20"
+                        prop:value=name
+                        on:input=move |ev| set_name.set(event_target_value(&ev))
+                    />
+                    <button on:click=handle_submit class="small-button">
+                        {move || t(locale.get(), "leaderboard.submit")}
+                    </button>
+                </div>
+            })}
+            <p>{move || message.get().unwrap_or_default()}</p>
+            <A href="/leaderboard">{move || t(locale.get(), "link.leaderboard")}</A>
+        </div>
+    }
+}
@@ -1,6 +1,37 @@
+pub mod agents;
+#[cfg(feature = "ssr")]
+pub mod api;
 pub mod app;
+pub mod board;
+pub mod canvas_board;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod arena;
+pub mod clipboard;
+pub mod daily;
+pub mod env;
 pub mod game;
+pub mod i18n;
+#[cfg(feature = "ssr")]
+pub mod game_store;
 pub mod index;
+pub mod inference;
+pub mod leaderboard;
+#[cfg(feature = "ssr")]
+pub mod leaderboard_db;
+pub mod leaderboard_view;
+pub mod link;
+pub mod online;
+pub mod palette;
+pub mod preset;
+pub mod protocol;
+pub mod scores;
+pub mod solver;
+pub mod stats;
+pub mod stats_view;
+pub mod tournament;
+pub mod tutorial;
+pub mod tutorial_view;
+pub mod worker;
 
 #[cfg(feature = "hydrate")]
 #[wasm_bindgen::prelude::wasm_bindgen]
@@ -0,0 +1,235 @@
+use crate::game::{Game, GameSettings, SettingsError};
+use crate::i18n::{self, t, tr, Locale};
+use crate::preset::Preset;
+
+// A game described by the query of a `/play?seed=…&w=…&h=…&preset=…` link
+pub struct PlayLink {
+    pub settings: GameSettings,
+    pub seed: u64,
+    // What could not be read from the link and was replaced by a default
+    pub notices: Vec<Notice>,
+}
+
+// Something `parse` could not read, and what it used instead
+#[derive(Clone, Debug, PartialEq)]
+pub enum Notice {
+    UnknownPreset(String),
+    // `field` is the query parameter, `w`, `h`, `attempts` or `busts`
+    InvalidNumber { field: &'static str, value: String, default: i32 },
+    InvalidSettings(SettingsError, Preset),
+    InvalidSeed,
+    MissingSeed,
+}
+
+impl Notice {
+    pub fn message(&self, locale: Locale) -> String {
+        match self {
+            Notice::UnknownPreset(name) => tr(locale, "notice.link.unknown_preset", &[("preset", name.clone())]),
+            Notice::InvalidNumber { field, value, default } => tr(
+                locale,
+                &format!("notice.link.invalid_{}", field),
+                &[("value", value.clone()), ("default", i18n::number(locale, default))],
+            ),
+            Notice::InvalidSettings(err, preset) => tr(
+                locale,
+                "notice.link.invalid_settings",
+                &[("error", err.message(locale)), ("preset", t(locale, &format!("preset.{}", preset)))],
+            ),
+            Notice::InvalidSeed => t(locale, "notice.link.invalid_seed"),
+            Notice::MissingSeed => t(locale, "notice.link.missing_seed"),
+        }
+    }
+}
+
+impl PlayLink {
+    pub fn game(&self) -> Game {
+        Game::from_seed(self.settings.clone(), self.seed)
+    }
+}
+
+// The preset whose sensor and hint rules the settings use, even when the board or the budget were
+// changed afterwards
+pub fn base_preset(settings: &GameSettings) -> Option<Preset> {
+    settings.preset.or_else(|| {
+        Preset::ALL.into_iter().find(|preset| {
+            let rules = preset.settings();
+            rules.conditional_probabilities == settings.conditional_probabilities
+                && rules.direction_reliability == settings.direction_reliability
+                && rules.allow_peep == settings.allow_peep
+        })
+    })
+}
+
+pub fn query(game: &Game) -> String {
+    let settings = &game.settings;
+    let preset = base_preset(settings).unwrap_or(Preset::Normal);
+    let mut query = format!("seed={}&w={}&h={}&preset={}", game.seed, settings.width, settings.height, preset);
+    let defaults = preset.settings();
+    if settings.score != defaults.score {
+        query.push_str(&format!("&attempts={}", settings.score));
+    }
+    if settings.busts != defaults.busts {
+        query.push_str(&format!("&busts={}", settings.busts));
+    }
+    query
+}
+
+// `get` looks up a query parameter. Anything missing or invalid falls back to the default and is
+// reported in `notices`.
+pub fn parse(get: impl Fn(&str) -> Option<String>) -> PlayLink {
+    let mut notices = vec![];
+
+    let preset = match get("preset") {
+        Some(name) => name.parse::<Preset>().unwrap_or_else(|_| {
+            notices.push(Notice::UnknownPreset(name));
+            Preset::Normal
+        }),
+        None => Preset::Normal,
+    };
+    let defaults = preset.settings();
+
+    let mut number = |field: &'static str, default: i32| match get(field) {
+        Some(value) => value.parse::<i32>().unwrap_or_else(|_| {
+            notices.push(Notice::InvalidNumber { field, value, default });
+            default
+        }),
+        None => default,
+    };
+    let mut settings = GameSettings {
+        width: number("w", defaults.width),
+        height: number("h", defaults.height),
+        score: number("attempts", defaults.score),
+        busts: number("busts", defaults.busts),
+        ..defaults.clone()
+    };
+    if let Err(err) = settings.validate() {
+        notices.push(Notice::InvalidSettings(err, preset));
+        settings = defaults.clone();
+    }
+    if (settings.width, settings.height, settings.score, settings.busts)
+        != (defaults.width, defaults.height, defaults.score, defaults.busts)
+    {
+        settings.preset = None;
+    }
+
+    let seed = match get("seed").map(|seed| seed.parse::<u64>()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(_)) => {
+            notices.push(Notice::InvalidSeed);
+            rand::random()
+        }
+        None => {
+            notices.push(Notice::MissingSeed);
+            rand::random()
+        }
+    };
+
+    PlayLink { settings, seed, notices }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MAX_BOARD_SIZE;
+    use std::collections::HashMap;
+
+    fn parse_query(query: &str) -> PlayLink {
+        let params = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        parse(|key| params.get(key).cloned())
+    }
+
+    #[test]
+    fn round_trips_a_preset_game() {
+        let game = Game::from_seed(Preset::Hard.settings(), 42);
+        let link = parse_query(&query(&game));
+        assert!(link.notices.is_empty(), "{:?}", link.notices);
+        assert_eq!(link.seed, 42);
+        assert_eq!(link.settings, Preset::Hard.settings());
+    }
+
+    #[test]
+    fn round_trips_a_custom_board_and_budget() {
+        let settings = GameSettings {
+            width: 20,
+            height: 7,
+            score: 12,
+            busts: 5,
+            preset: None,
+            ..Preset::Easy.settings()
+        };
+        let game = Game::from_seed(settings.clone(), u64::MAX);
+        let link = parse_query(&query(&game));
+        assert!(link.notices.is_empty(), "{:?}", link.notices);
+        assert_eq!(link.seed, u64::MAX);
+        assert_eq!(link.settings, settings);
+        assert_eq!(base_preset(&link.settings), Some(Preset::Easy));
+    }
+
+    #[test]
+    fn a_bad_seed_plays_a_random_board_with_the_settings() {
+        let link = parse_query("seed=ghost&w=10&h=8&preset=easy");
+        assert_eq!(link.notices, vec![Notice::InvalidSeed]);
+        assert_eq!((link.settings.width, link.settings.height), (10, 8));
+        assert_eq!(link.settings.preset, None);
+    }
+
+    #[test]
+    fn a_missing_seed_is_reported() {
+        let link = parse_query("w=8&h=6&preset=easy");
+        assert_eq!(link.notices, vec![Notice::MissingSeed]);
+        assert_eq!(link.settings, Preset::Easy.settings());
+    }
+
+    #[test]
+    fn an_oversized_board_falls_back_to_the_preset() {
+        let link = parse_query(&format!("seed=1&w={}&h=10&preset=hard", MAX_BOARD_SIZE + 1));
+        assert_eq!(link.notices, vec![Notice::InvalidSettings(SettingsError::BoardTooLarge, Preset::Hard)]);
+        assert_eq!(link.settings, Preset::Hard.settings());
+        assert_eq!(link.seed, 1);
+    }
+
+    #[test]
+    fn an_invalid_number_falls_back_to_its_default() {
+        let link = parse_query("seed=1&w=wide&h=9&preset=normal");
+        assert_eq!(
+            link.notices,
+            vec![Notice::InvalidNumber {
+                field: "w",
+                value: "wide".to_string(),
+                default: Preset::Normal.settings().width
+            }]
+        );
+        assert_eq!(link.settings, Preset::Normal.settings());
+    }
+
+    #[test]
+    fn an_unknown_preset_uses_normal() {
+        let link = parse_query("seed=3&w=12&h=9&preset=impossible");
+        assert_eq!(link.notices, vec![Notice::UnknownPreset("impossible".to_string())]);
+        assert_eq!(link.settings, Preset::Normal.settings());
+    }
+
+    #[test]
+    fn every_notice_has_a_message_in_every_locale() {
+        let notices = [
+            Notice::UnknownPreset("impossible".to_string()),
+            Notice::InvalidNumber { field: "w", value: "wide".to_string(), default: 12 },
+            Notice::InvalidNumber { field: "h", value: "tall".to_string(), default: 9 },
+            Notice::InvalidNumber { field: "attempts", value: "many".to_string(), default: 10 },
+            Notice::InvalidNumber { field: "busts", value: "few".to_string(), default: 2 },
+            Notice::InvalidSettings(SettingsError::BoardTooSmall, Preset::Easy),
+            Notice::InvalidSeed,
+            Notice::MissingSeed,
+        ];
+        for locale in Locale::ALL {
+            for notice in &notices {
+                let message = notice.message(locale);
+                assert!(!message.starts_with("notice.") && !message.contains('{'), "{:?}: {}", locale, message);
+            }
+        }
+    }
+}
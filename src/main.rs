@@ -24,6 +24,10 @@ async fn main() -> std::io::Result<()> {
             .service(Files::new("/assets", site_root))
             // serve the favicon from /favicon.ico
             .service(favicon)
+            // the REST API for bots
+            .configure(bust_ghost::api::routes)
+            // server functions, e.g. the moves of online games
+            .route("/api/{tail:.*}", leptos_actix::handle_server_fns())
             .leptos_routes(leptos_options.to_owned(), routes.to_owned(), App)
             .app_data(web::Data::new(leptos_options.to_owned()))
         //.wrap(middleware::Compress::default())
@@ -45,15 +49,15 @@ async fn favicon(
     ))?)
 }
 
-// #[cfg(not(any(feature = "ssr", feature = "csr")))]
-// pub fn main() {
+#[cfg(not(any(feature = "ssr", feature = "csr")))]
+pub fn main() {
     // no client-side main function
     // unless we want this to work with e.g., Trunk for pure client-side testing
     // see lib.rs for hydration function instead
     // see optional feature `csr` instead
-// }
+}
 
-// #[cfg(all(not(feature = "ssr"), feature = "csr"))]
+#[cfg(all(not(feature = "ssr"), feature = "csr"))]
 pub fn main() {
     // a client-side main function is required for using `trunk serve`
     // prefer using `cargo leptos serve` instead
@@ -0,0 +1,104 @@
+use crate::game::{Event, GameSettings, Status};
+use crate::preset::Preset;
+#[cfg(feature = "ssr")]
+use crate::game::{Action, Game};
+use leptos::server_fn::codec::Json;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+// A game played against the server: the server holds the ghost and draws the readings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OnlineGame {
+    pub id: String,
+    pub settings: GameSettings,
+}
+
+// What the client learns from an action
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Turn {
+    pub event: Event,
+    pub score: i32,
+    pub busts: i32,
+    pub status: Status,
+    // Only sent once the game is over, when it can't be used to cheat any more
+    pub reveal: Option<Reveal>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reveal {
+    pub seed: u64,
+    pub ghost_position: (i32, i32),
+}
+
+#[cfg(feature = "ssr")]
+impl Turn {
+    pub fn new(game: &Game, event: Event) -> Turn {
+        Turn {
+            event,
+            score: game.score,
+            busts: game.busts,
+            status: game.status,
+            reveal: (game.status != Status::Playing).then_some(Reveal {
+                seed: game.seed,
+                ghost_position: game.ghost_position,
+            }),
+        }
+    }
+}
+
+// Only the board size can be picked besides the preset: the sensor and the budget are the preset's,
+// so that nobody gets to make online games easy
+#[server(prefix = "/api", endpoint = "new_online_game", input = Json)]
+pub async fn new_online_game(preset: Preset, width: i32, height: i32) -> Result<OnlineGame, ServerFnError> {
+    let new_game = crate::protocol::NewGame {
+        preset: Some(preset),
+        seed: None,
+        width: Some(width),
+        height: Some(height),
+    };
+    let mut game = new_game.game().map_err(ServerFnError::new)?;
+    game.place_ghost();
+    game.compute_initial_prior_probabilities();
+    let settings = game.settings.clone();
+    let id = crate::game_store::insert(game, crate::game_store::Ranking::Ranked, &client().await?)
+        .map_err(ServerFnError::new)?;
+    Ok(OnlineGame { id, settings })
+}
+
+// A game of the day's ranked challenge, which only ranks on the board of the server's date it was
+// created on. Every game draws its own ghost: one ghost for everyone could be found by playing the
+// day's game a few times before the one that counts.
+#[server(prefix = "/api", endpoint = "new_daily_game", input = Json)]
+pub async fn new_daily_game() -> Result<OnlineGame, ServerFnError> {
+    let date = crate::daily::server_today();
+    let mut game = Game::from_seed(crate::daily::settings(), rand::random());
+    game.place_ghost();
+    game.compute_initial_prior_probabilities();
+    let settings = game.settings.clone();
+    let id = crate::game_store::insert(game, crate::game_store::Ranking::Daily(date), &client().await?)
+        .map_err(ServerFnError::new)?;
+    Ok(OnlineGame { id, settings })
+}
+
+#[cfg(feature = "ssr")]
+async fn client() -> Result<String, ServerFnError> {
+    let request = leptos_actix::extract::<actix_web::HttpRequest>().await?;
+    Ok(crate::game_store::client(&request))
+}
+
+#[cfg(feature = "ssr")]
+fn play(id: &str, action: Action) -> Result<Turn, ServerFnError> {
+    crate::game_store::play(id, action, Turn::new)
+        .unwrap_or_else(|| Err("No such game, it may have expired".to_string()))
+        .map_err(ServerFnError::new)
+}
+
+#[server(prefix = "/api", endpoint = "sense_online")]
+pub async fn sense_online(id: String, x: i32, y: i32) -> Result<Turn, ServerFnError> {
+    play(&id, Action::Sense { x, y })
+}
+
+#[server(prefix = "/api", endpoint = "bust_online")]
+pub async fn bust_online(id: String, x: i32, y: i32) -> Result<Turn, ServerFnError> {
+    play(&id, Action::Bust { x, y })
+}
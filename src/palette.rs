@@ -0,0 +1,191 @@
+use crate::game::SensorColor;
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const APPEARANCE_KEY: &str = "bust-ghost.appearance";
+
+// The colors the readings are drawn in. Every palette but the default keeps the four readings
+// apart for one kind of color blindness, mostly by lightness, and has share emoji to match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Default,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Default,
+        Palette::Deuteranopia,
+        Palette::Protanopia,
+        Palette::Tritanopia,
+        Palette::HighContrast,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia => "protanopia",
+            Palette::Tritanopia => "tritanopia",
+            Palette::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Palette::Default => "Default",
+            Palette::Deuteranopia => "Deuteranopia",
+            Palette::Protanopia => "Protanopia",
+            Palette::Tritanopia => "Tritanopia",
+            Palette::HighContrast => "High contrast",
+        }
+    }
+
+    // In the order of `SensorColor::ALL`, from the farthest reading to the closest
+    fn colors(&self) -> [[u8; 3]; 4] {
+        match self {
+            Palette::Default => [[0, 128, 0], [255, 255, 0], [255, 165, 0], [255, 0, 0]],
+            Palette::Deuteranopia => [[0, 90, 181], [255, 235, 130], [230, 159, 0], [90, 60, 30]],
+            Palette::Protanopia => [[0, 114, 178], [240, 228, 66], [204, 121, 167], [60, 60, 60]],
+            Palette::Tritanopia => [[0, 158, 115], [204, 121, 167], [213, 60, 0], [40, 40, 40]],
+            Palette::HighContrast => [[0, 0, 0], [0, 60, 255], [255, 255, 0], [255, 0, 0]],
+        }
+    }
+
+    fn emoji(&self) -> [char; 4] {
+        match self {
+            Palette::Default => ['🟩', '🟨', '🟧', '🟥'],
+            Palette::Deuteranopia => ['🟦', '🟨', '🟧', '🟫'],
+            Palette::Protanopia => ['🟦', '🟨', '🟪', '⬛'],
+            Palette::Tritanopia => ['🟩', '🟪', '🟥', '⬛'],
+            Palette::HighContrast => ['⬛', '🟦', '🟨', '🟥'],
+        }
+    }
+
+    // Unsensed cells are white in every palette
+    pub fn rgb(&self, color: Option<SensorColor>) -> [u8; 3] {
+        color.map_or([255, 255, 255], |color| self.colors()[color as usize])
+    }
+
+    pub fn css(&self, color: Option<SensorColor>) -> String {
+        let [r, g, b] = self.rgb(color);
+        format!("rgb({}, {}, {})", r, g, b)
+    }
+
+    // Black or white, whichever reads better on the cell
+    pub fn text(&self, color: Option<SensorColor>) -> &'static str {
+        let [r, g, b] = self.rgb(color);
+        if 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64 > 128.0 {
+            "black"
+        } else {
+            "white"
+        }
+    }
+
+    pub fn share(&self, color: Option<SensorColor>) -> char {
+        color.map_or('⬜', |color| self.emoji()[color as usize])
+    }
+
+    // The CSS custom properties the `cell--sensed-<color>` classes are drawn with
+    pub fn css_variables(&self) -> String {
+        SensorColor::ALL
+            .into_iter()
+            .map(|color| {
+                format!(
+                    "--sensed-{}: {}; --sensed-{}-text: {};",
+                    color.name(),
+                    self.css(Some(color)),
+                    color.name(),
+                    self.text(Some(color))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// Light or dark, or whichever the system prefers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    // The `data-theme` of <html>, the stylesheet only overrides the system for "light" and "dark"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::System => "system",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+}
+
+// A symbol per reading, drawn over the color so that the readings don't rely on color alone. The
+// fuller the circle, the closer the ghost.
+pub fn glyph(color: Option<SensorColor>) -> char {
+    match color {
+        Some(SensorColor::Green) => '○',
+        Some(SensorColor::Yellow) => '◔',
+        Some(SensorColor::Orange) => '◑',
+        Some(SensorColor::Red) => '●',
+        None => '·',
+    }
+}
+
+// How the player wants the game to look, kept in local storage. `App` shares it with every page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Appearance {
+    pub palette: Palette,
+    pub glyphs: bool,
+    pub theme: Theme,
+}
+
+impl Appearance {
+    // A cell of a share string: its glyph when glyphs are on, or the palette's emoji
+    pub fn share(&self, color: Option<SensorColor>) -> char {
+        if self.glyphs {
+            glyph(color)
+        } else {
+            self.palette.share(color)
+        }
+    }
+}
+
+pub fn load() -> Appearance {
+    LocalStorage::get(APPEARANCE_KEY).unwrap_or_default()
+}
+
+pub fn save(appearance: Appearance) {
+    if let Err(err) = LocalStorage::set(APPEARANCE_KEY, appearance) {
+        gloo::console::error!(format!("Could not save the appearance: {}", err));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::game::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    Easy,
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Easy, Preset::Normal, Preset::Hard, Preset::Nightmare];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Easy => "easy",
+            Preset::Normal => "normal",
+            Preset::Hard => "hard",
+            Preset::Nightmare => "nightmare",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Preset::Easy => "Easy",
+            Preset::Normal => "Normal",
+            Preset::Hard => "Hard",
+            Preset::Nightmare => "Nightmare",
+        }
+    }
+
+    pub fn settings(&self) -> GameSettings {
+        // Each row is the probability of sensing green, yellow, orange and red at a given
+        // distance from the ghost. The harder the preset, the flatter the rows.
+        let (width, height, score, busts, direction_reliability, allow_peep, rows) = match self {
+            Preset::Easy => (8, 6, 30, 3, 1.0, true, [
+                [0.02, 0.03, 0.05, 0.90],
+                [0.03, 0.07, 0.85, 0.05],
+                [0.03, 0.07, 0.85, 0.05],
+                [0.05, 0.85, 0.07, 0.03],
+                [0.05, 0.85, 0.07, 0.03],
+                [0.90, 0.05, 0.03, 0.02],
+            ]),
+            Preset::Normal => (12, 9, 30, 2, 0.9, true, [
+                [0.05, 0.05, 0.10, 0.80],
+                [0.05, 0.10, 0.75, 0.10],
+                [0.05, 0.10, 0.75, 0.10],
+                [0.10, 0.70, 0.15, 0.05],
+                [0.10, 0.70, 0.15, 0.05],
+                [0.70, 0.10, 0.10, 0.05],
+            ]),
+            Preset::Hard => (16, 12, 25, 2, 0.75, false, [
+                [0.10, 0.10, 0.20, 0.60],
+                [0.10, 0.20, 0.55, 0.15],
+                [0.10, 0.20, 0.55, 0.15],
+                [0.20, 0.50, 0.20, 0.10],
+                [0.20, 0.50, 0.20, 0.10],
+                [0.55, 0.20, 0.15, 0.10],
+            ]),
+            Preset::Nightmare => (20, 16, 25, 1, 0.6, false, [
+                [0.15, 0.15, 0.25, 0.45],
+                [0.15, 0.25, 0.40, 0.20],
+                [0.15, 0.25, 0.40, 0.20],
+                [0.25, 0.40, 0.20, 0.15],
+                [0.25, 0.40, 0.20, 0.15],
+                [0.40, 0.25, 0.20, 0.15],
+            ]),
+        };
+        let conditional_probabilities = rows
+            .iter()
+            .enumerate()
+            .map(|(distance, [green, yellow, orange, red])| {
+                ConditionalProbabilities::new(distance as i32, *green, *yellow, *orange, *red)
+            })
+            .collect();
+        GameSettings {
+            width,
+            height,
+            score,
+            busts,
+            conditional_probabilities,
+            direction_reliability,
+            allow_peep,
+            preset: Some(*self),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown preset '{}', expected one of easy, normal, hard or nightmare", s))
+    }
+}
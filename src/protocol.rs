@@ -0,0 +1,276 @@
+// The line protocol bots speak to play without a browser, like UCI for chess engines. The bot
+// sends commands and the engine answers each of them with one line:
+//
+//   isready                                     readyok
+//   newgame [preset=P] [seed=S] [width=W] [height=H]
+//                                               game <preset> <width> <height> <attempts> <busts>
+//   sense <x> <y>                               reading <x> <y> <color> <direction> <attempts> <busts> <status>
+//   bust <x> <y>                                bust <x> <y> <hit|miss> <attempts> <busts> <status>
+//   quit
+//
+// Commands that can't be played are answered with `error <message>`. See docs/protocol.md.
+use crate::game::{Action, Event, Game, GameSettings, Status};
+use crate::preset::Preset;
+use serde::Deserialize;
+use std::fmt;
+
+// Every field falls back to the normal preset, or to a random seed
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct NewGame {
+    pub preset: Option<Preset>,
+    pub seed: Option<u64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+impl NewGame {
+    pub fn settings(&self) -> Result<GameSettings, String> {
+        let preset = self.preset.unwrap_or(Preset::Normal);
+        let mut settings = GameSettings {
+            width: self.width.unwrap_or(preset.settings().width),
+            height: self.height.unwrap_or(preset.settings().height),
+            ..preset.settings()
+        };
+        settings.validate()?;
+        if settings != preset.settings() {
+            settings.preset = None;
+        }
+        Ok(settings)
+    }
+
+    pub fn game(&self) -> Result<Game, String> {
+        Ok(Game::from_seed(self.settings()?, self.seed.unwrap_or_else(rand::random)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    IsReady,
+    NewGame(NewGame),
+    Play(Action),
+    Quit,
+}
+
+// The line a bot sends for the command, which `parse` reads back
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::IsReady => write!(f, "isready"),
+            Command::Quit => write!(f, "quit"),
+            Command::Play(Action::Sense { x, y }) => write!(f, "sense {} {}", x, y),
+            Command::Play(Action::Bust { x, y }) => write!(f, "bust {} {}", x, y),
+            Command::NewGame(new_game) => {
+                write!(f, "newgame")?;
+                if let Some(preset) = new_game.preset {
+                    write!(f, " preset={}", preset.name())?;
+                }
+                if let Some(seed) = new_game.seed {
+                    write!(f, " seed={}", seed)?;
+                }
+                if let Some(width) = new_game.width {
+                    write!(f, " width={}", width)?;
+                }
+                if let Some(height) = new_game.height {
+                    write!(f, " height={}", height)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("'{}' is not a valid {}", value, name))
+}
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["isready"] => Ok(Command::IsReady),
+        ["quit"] => Ok(Command::Quit),
+        ["sense", x, y] => Ok(Command::Play(Action::Sense {
+            x: parse_number("x", x)?,
+            y: parse_number("y", y)?,
+        })),
+        ["bust", x, y] => Ok(Command::Play(Action::Bust {
+            x: parse_number("x", x)?,
+            y: parse_number("y", y)?,
+        })),
+        ["newgame", options @ ..] => {
+            let mut new_game = NewGame::default();
+            for option in options {
+                match option.split_once('=') {
+                    Some(("preset", name)) => new_game.preset = Some(name.parse()?),
+                    Some(("seed", seed)) => new_game.seed = Some(parse_number("seed", seed)?),
+                    Some(("width", width)) => new_game.width = Some(parse_number("width", width)?),
+                    Some(("height", height)) => new_game.height = Some(parse_number("height", height)?),
+                    _ => return Err(format!("Unknown option '{}'", option)),
+                }
+            }
+            Ok(Command::NewGame(new_game))
+        }
+        [] => Err("Empty command".to_string()),
+        [command, ..] => Err(format!("Unknown command '{}'", command)),
+    }
+}
+
+fn status_name(status: Status) -> &'static str {
+    match status {
+        Status::Playing => "playing",
+        Status::Won => "won",
+        Status::Lost => "lost",
+    }
+}
+
+// The answer to `newgame`
+pub fn game_line(game: &Game) -> String {
+    format!(
+        "game {} {} {} {} {}",
+        game.settings.preset.map(|preset| preset.name()).unwrap_or("custom"),
+        game.columns(),
+        game.rows(),
+        game.score,
+        game.busts
+    )
+}
+
+// The answer to `sense` and `bust`, `game` is the game after the action
+pub fn event_line(game: &Game, event: &Event) -> String {
+    let status = status_name(game.status);
+    match event {
+        Event::Reading { x, y, color, direction } => format!(
+            "reading {} {} {} {} {} {} {}",
+            x, y, color, direction, game.score, game.busts, status
+        ),
+        Event::Bust { x, y, hit } => format!(
+            "bust {} {} {} {} {} {}",
+            x,
+            y,
+            if *hit { "hit" } else { "miss" },
+            game.score,
+            game.busts,
+            status
+        ),
+    }
+}
+
+// A game being played over the protocol
+#[derive(Default)]
+pub struct Session {
+    pub game: Option<Game>,
+}
+
+impl Session {
+    pub fn start(&mut self, mut game: Game) -> String {
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        let line = game_line(&game);
+        self.game = Some(game);
+        line
+    }
+
+    pub fn play(&mut self, action: Action) -> String {
+        let Some(game) = &mut self.game else {
+            return "error No game, send newgame first".to_string();
+        };
+        match game.apply(action) {
+            Ok(event) => event_line(game, &event),
+            Err(err) => format!("error {}", err),
+        }
+    }
+
+    pub fn status(&self) -> Option<Status> {
+        self.game.as_ref().map(|game| game.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(command: Command) {
+        assert_eq!(parse(&command.to_string()), Ok(command));
+    }
+
+    #[test]
+    fn isready_and_quit_round_trip() {
+        round_trip(Command::IsReady);
+        round_trip(Command::Quit);
+    }
+
+    #[test]
+    fn sense_and_bust_round_trip() {
+        round_trip(Command::Play(Action::Sense { x: 0, y: 12 }));
+        round_trip(Command::Play(Action::Bust { x: 499, y: 3 }));
+        // out of the board is for the game to refuse, not the parser
+        round_trip(Command::Play(Action::Sense { x: -1, y: 2 }));
+    }
+
+    #[test]
+    fn newgame_round_trips_with_any_options() {
+        round_trip(Command::NewGame(NewGame::default()));
+        round_trip(Command::NewGame(NewGame {
+            preset: Some(Preset::Hard),
+            seed: Some(u64::MAX),
+            width: Some(20),
+            height: Some(10),
+        }));
+        round_trip(Command::NewGame(NewGame {
+            seed: Some(42),
+            ..NewGame::default()
+        }));
+    }
+
+    #[test]
+    fn newgame_options_come_in_any_order() {
+        let expected = NewGame {
+            preset: Some(Preset::Easy),
+            seed: Some(7),
+            width: None,
+            height: Some(4),
+        };
+        assert_eq!(parse("newgame height=4 seed=7 preset=easy"), Ok(Command::NewGame(expected)));
+    }
+
+    #[test]
+    fn extra_whitespace_is_ignored() {
+        assert_eq!(parse("  sense\t1   2 \r"), Ok(Command::Play(Action::Sense { x: 1, y: 2 })));
+        assert_eq!(parse("isready\n"), Ok(Command::IsReady));
+    }
+
+    #[test]
+    fn empty_and_unknown_commands_are_rejected() {
+        assert_eq!(parse(""), Err("Empty command".to_string()));
+        assert_eq!(parse("   "), Err("Empty command".to_string()));
+        assert_eq!(parse("peep 1 2"), Err("Unknown command 'peep'".to_string()));
+        // commands are case sensitive
+        assert!(parse("ISREADY").is_err());
+    }
+
+    #[test]
+    fn isready_and_quit_take_no_arguments() {
+        assert!(parse("isready now").is_err());
+        assert!(parse("quit 1").is_err());
+    }
+
+    #[test]
+    fn sense_and_bust_need_two_integers() {
+        for command in ["sense", "bust"] {
+            assert!(parse(command).is_err());
+            assert!(parse(&format!("{} 1", command)).is_err());
+            assert!(parse(&format!("{} 1 2 3", command)).is_err());
+            assert_eq!(parse(&format!("{} 1 b", command)), Err("'b' is not a valid y".to_string()));
+            assert_eq!(parse(&format!("{} 1.5 2", command)), Err("'1.5' is not a valid x".to_string()));
+        }
+    }
+
+    #[test]
+    fn malformed_newgame_options_are_rejected() {
+        assert_eq!(parse("newgame size=3"), Err("Unknown option 'size=3'".to_string()));
+        assert_eq!(parse("newgame hard"), Err("Unknown option 'hard'".to_string()));
+        assert_eq!(parse("newgame seed=-1"), Err("'-1' is not a valid seed".to_string()));
+        assert_eq!(parse("newgame width=wide"), Err("'wide' is not a valid width".to_string()));
+        assert_eq!(parse("newgame height="), Err("'' is not a valid height".to_string()));
+        assert!(parse("newgame preset=impossible").is_err());
+    }
+}
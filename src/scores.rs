@@ -0,0 +1,55 @@
+use crate::preset::Preset;
+use gloo::storage::{LocalStorage, Storage};
+use std::collections::HashMap;
+
+const HIGH_SCORES_KEY: &str = "bust-ghost.high-scores";
+
+// Best number of attempts left when winning, keyed by preset name. Custom games have no preset
+// and are not ranked.
+pub fn high_scores() -> HashMap<String, i32> {
+    LocalStorage::get(HIGH_SCORES_KEY).unwrap_or_default()
+}
+
+pub fn high_score(preset: Preset) -> Option<i32> {
+    high_scores().get(preset.name()).copied()
+}
+
+// Returns true when `score` beats the stored high score for the preset
+pub fn record_win(preset: Preset, score: i32) -> bool {
+    let mut scores = high_scores();
+    if !beat(&mut scores, preset, score) {
+        return false;
+    }
+    if let Err(err) = LocalStorage::set(HIGH_SCORES_KEY, scores) {
+        gloo::console::error!(format!("Could not save high scores: {}", err));
+    }
+    true
+}
+
+// Keeps `score` as the high score of the preset when it beats it, wherever the scores are stored:
+// the page keeps them in local storage and the terminal game in a file
+pub fn beat(scores: &mut HashMap<String, i32>, preset: Preset, score: i32) -> bool {
+    let best = scores.entry(preset.name().to_string()).or_insert(-1);
+    if score <= *best {
+        return false;
+    }
+    *best = score;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_better_score_beats_the_high_score_of_its_preset() {
+        let mut scores = HashMap::new();
+        assert!(beat(&mut scores, Preset::Hard, 0));
+        assert!(beat(&mut scores, Preset::Hard, 3));
+        assert!(!beat(&mut scores, Preset::Hard, 3));
+        assert!(!beat(&mut scores, Preset::Hard, 2));
+        assert!(beat(&mut scores, Preset::Easy, 1));
+        assert_eq!(scores.get("hard"), Some(&3));
+        assert_eq!(scores.get("easy"), Some(&1));
+    }
+}
@@ -0,0 +1,366 @@
+// Computes the optimal policy of small boards by expectimax over beliefs: the probability of the
+// ghost being in each cell, with the attempts and busts left. Beliefs are rounded to
+// `resolution` to memoise the values of beliefs that are almost the same.
+use crate::game::{sensor_row, Action, Direction, GameSettings, SensorColor, DIRECTIONS};
+use std::collections::HashMap;
+
+// Bigger boards have too many beliefs to explore
+pub const MAX_CELLS: usize = 16;
+const COLORS: [&str; 4] = ["green", "yellow", "orange", "red"];
+
+#[derive(Clone, Debug)]
+pub struct Solution {
+    // The probability of winning when playing optimally from the start
+    pub win_probability: f64,
+    pub first_action: Action,
+    // How many beliefs were memoised
+    pub states: usize,
+}
+
+pub struct Solver {
+    rows: i32,
+    columns: i32,
+    attempts: i32,
+    busts: i32,
+    // likelihoods[c][o][g] is the probability of the reading o (a color and a direction, see
+    // `outcome`) when sensing the cell c with the ghost on the cell g
+    likelihoods: Vec<Vec<Vec<f64>>>,
+    pub resolution: f64,
+    memo: HashMap<(Vec<u32>, i32, i32), f64>,
+}
+
+// The index of a reading in `Solver::likelihoods`
+fn outcome(color: usize, direction: usize) -> usize {
+    color * DIRECTIONS.len() + direction
+}
+
+impl Solver {
+    pub fn new(settings: &GameSettings) -> Result<Solver, String> {
+        settings.validate()?;
+        let (rows, columns) = (settings.height, settings.width);
+        let cells = (rows * columns) as usize;
+        if cells > MAX_CELLS {
+            return Err(format!("The solver handles boards of up to {} cells, not {}", MAX_CELLS, cells));
+        }
+        let table = &settings.conditional_probabilities;
+        let reliability = settings.direction_reliability as f64;
+        let cell = |i: usize| (i as i32 / columns, i as i32 % columns);
+        let likelihood = |c: usize, outcome: usize, g: usize| {
+            let ((x, y), (gx, gy)) = (cell(c), cell(g));
+            let (color, direction) = (outcome / DIRECTIONS.len(), outcome % DIRECTIONS.len());
+            // Colors are drawn like `Game::distance_sense` does, each row of the table being weights
+            let row = &table[sensor_row(table, (gx - x).abs() + (gy - y).abs())];
+            let color_probability = row.likelihood(SensorColor::ALL[color]);
+            let direction_probability = if Direction::ALL[direction] == Direction::between(x, y, gx, gy) {
+                reliability
+            } else {
+                (1.0 - reliability) / (DIRECTIONS.len() - 1) as f64
+            };
+            color_probability * direction_probability
+        };
+        let likelihoods = (0..cells)
+            .map(|c| {
+                (0..COLORS.len() * DIRECTIONS.len())
+                    .map(|outcome| (0..cells).map(|g| likelihood(c, outcome, g)).collect())
+                    .collect()
+            })
+            .collect();
+        Ok(Solver {
+            rows,
+            columns,
+            attempts: settings.score,
+            busts: settings.busts,
+            likelihoods,
+            resolution: 1000.0,
+            memo: HashMap::new(),
+        })
+    }
+
+    fn cell(&self, i: usize) -> (i32, i32) {
+        (i as i32 / self.columns, i as i32 % self.columns)
+    }
+
+    pub fn uniform_belief(&self) -> Vec<f64> {
+        let cells = (self.rows * self.columns) as usize;
+        vec![1.0 / cells as f64; cells]
+    }
+
+    // The belief after reading `color` and `direction` on the cell (x, y)
+    pub fn update(&self, belief: &[f64], x: i32, y: i32, color: &str, direction: &str) -> Vec<f64> {
+        let color = COLORS.iter().position(|c| *c == color).unwrap_or(0);
+        let direction = DIRECTIONS.iter().position(|d| *d == direction).unwrap_or(0);
+        let likelihoods = &self.likelihoods[(x * self.columns + y) as usize][outcome(color, direction)];
+        normalize(belief.iter().zip(likelihoods).map(|(p, l)| p * l).collect()).unwrap_or_else(|| belief.to_vec())
+    }
+
+    // The belief after a missed bust of the cell (x, y)
+    pub fn rule_out(&self, belief: &[f64], x: i32, y: i32) -> Vec<f64> {
+        let mut belief = belief.to_vec();
+        belief[(x * self.columns + y) as usize] = 0.0;
+        normalize(belief.clone()).unwrap_or(belief)
+    }
+
+    pub fn solve(&mut self) -> Solution {
+        let (first_action, win_probability) = self.best_action(&self.uniform_belief(), self.attempts, self.busts);
+        Solution {
+            win_probability,
+            first_action,
+            states: self.memo.len(),
+        }
+    }
+
+    // The optimal action with `attempts` and `busts` left, and the probability of winning with it
+    pub fn best_action(&mut self, belief: &[f64], attempts: i32, busts: i32) -> (Action, f64) {
+        let mut best = (Action::Bust { x: 0, y: 0 }, -1.0);
+        if busts == 0 {
+            return (best.0, 0.0);
+        }
+        for (c, p) in belief.iter().enumerate() {
+            if *p <= 0.0 {
+                continue;
+            }
+            let (x, y) = self.cell(c);
+            let mut value = *p;
+            if busts > 1 && *p < 1.0 {
+                value += (1.0 - p) * self.value(&self.rule_out(belief, x, y), attempts, busts - 1);
+            }
+            if value > best.1 {
+                best = (Action::Bust { x, y }, value);
+            }
+        }
+        // Sensing with the last attempt loses the game
+        if attempts > 1 && best.1 < 1.0 {
+            for c in 0..belief.len() {
+                let value = if attempts == 2 {
+                    self.last_sense_value(belief, c, busts, best.1)
+                } else {
+                    self.sense_value(belief, c, attempts, busts, best.1)
+                };
+                if value > best.1 + 1e-12 {
+                    let (x, y) = self.cell(c);
+                    best = (Action::Sense { x, y }, value);
+                }
+            }
+        }
+        best
+    }
+
+    // Stops early, with a value of at most `bound`, once sensing the cell can't do better than `bound`
+    fn sense_value(&mut self, belief: &[f64], c: usize, attempts: i32, busts: i32, bound: f64) -> f64 {
+        let mut readings = self.likelihoods[c]
+            .iter()
+            .filter_map(|likelihoods| {
+                let joint = belief.iter().zip(likelihoods).map(|(p, l)| p * l).collect::<Vec<_>>();
+                let probability = joint.iter().sum::<f64>();
+                (probability > 1e-12).then_some((probability, joint))
+            })
+            .collect::<Vec<_>>();
+        // the likeliest readings first, as they settle the bound sooner
+        readings.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut value = 0.0;
+        let mut unexplored = 1.0;
+        for (probability, mut posterior) in readings {
+            if value + unexplored <= bound {
+                break;
+            }
+            posterior.iter_mut().for_each(|p| *p /= probability);
+            value += probability * self.value(&posterior, attempts - 1, busts);
+            unexplored -= probability;
+        }
+        value
+    }
+
+    // Sensing with the last attempt but one, after which the best is to bust the most likely
+    // cells: the chance of winning after each reading is the sum of the `busts` largest
+    // probabilities. Stops early like `sense_value`.
+    fn last_sense_value(&self, belief: &[f64], c: usize, busts: i32, bound: f64) -> f64 {
+        let mut joint = [0.0; MAX_CELLS];
+        let joint = &mut joint[..belief.len()];
+        let mut value = 0.0;
+        let mut unexplored = 1.0;
+        for likelihoods in &self.likelihoods[c] {
+            if value + unexplored <= bound {
+                break;
+            }
+            for ((joint, p), l) in joint.iter_mut().zip(belief).zip(likelihoods) {
+                *joint = p * l;
+            }
+            unexplored -= joint.iter().sum::<f64>();
+            value += largest_sum(joint, busts);
+        }
+        value
+    }
+
+    // The probability of winning from this belief when playing optimally
+    pub fn value(&mut self, belief: &[f64], attempts: i32, busts: i32) -> f64 {
+        // Busting every cell the ghost may be on is a sure win
+        if belief.iter().filter(|p| **p > 0.0).count() <= busts as usize {
+            return 1.0;
+        }
+        // Without attempts to sense, the best is to bust the most likely cells
+        if attempts <= 1 {
+            return largest_sum(&mut belief.to_vec(), busts);
+        }
+        let key = (
+            belief.iter().map(|p| (p * self.resolution).round() as u32).collect::<Vec<_>>(),
+            attempts,
+            busts,
+        );
+        if let Some(value) = self.memo.get(&key) {
+            return *value;
+        }
+        let (_, value) = self.best_action(belief, attempts, busts);
+        self.memo.insert(key, value);
+        value
+    }
+}
+
+// The sum of the `count` largest values, which are moved to the front
+fn largest_sum(values: &mut [f64], count: i32) -> f64 {
+    let count = (count.max(0) as usize).min(values.len());
+    if count == 0 {
+        return 0.0;
+    }
+    if count < values.len() {
+        values.select_nth_unstable_by(count - 1, |a, b| b.total_cmp(a));
+    }
+    values[..count].iter().sum()
+}
+
+fn normalize(mut belief: Vec<f64>) -> Option<Vec<f64>> {
+    let total = belief.iter().sum::<f64>();
+    if total <= 0.0 {
+        return None;
+    }
+    belief.iter_mut().for_each(|p| *p /= total);
+    Some(belief)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::preset::Preset;
+
+    fn tiny(preset: Preset, attempts: i32, busts: i32) -> GameSettings {
+        GameSettings {
+            width: 3,
+            height: 3,
+            score: attempts,
+            busts,
+            preset: None,
+            ..preset.settings()
+        }
+    }
+
+    // Expectimax straight from the rules: every bust and every sense with every reading, without
+    // memoising, bounds or shortcuts
+    fn brute_force(solver: &Solver, belief: &[f64], attempts: i32, busts: i32) -> f64 {
+        if busts == 0 {
+            return 0.0;
+        }
+        let mut best = 0.0f64;
+        for (c, p) in belief.iter().enumerate() {
+            if *p <= 0.0 {
+                continue;
+            }
+            let (x, y) = solver.cell(c);
+            let mut value = *p;
+            if *p < 1.0 {
+                value += (1.0 - p) * brute_force(solver, &solver.rule_out(belief, x, y), attempts, busts - 1);
+            }
+            best = best.max(value);
+        }
+        if attempts > 1 {
+            for likelihoods in &solver.likelihoods {
+                let mut value = 0.0;
+                for likelihoods in likelihoods {
+                    let joint = belief.iter().zip(likelihoods).map(|(p, l)| p * l).collect::<Vec<_>>();
+                    let probability = joint.iter().sum::<f64>();
+                    if probability > 1e-12 {
+                        let posterior = joint.iter().map(|p| p / probability).collect::<Vec<_>>();
+                        value += probability * brute_force(solver, &posterior, attempts - 1, busts);
+                    }
+                }
+                best = best.max(value);
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn expectimax_matches_brute_force_on_a_tiny_board() {
+        for (preset, attempts, busts) in [(Preset::Hard, 3, 1), (Preset::Normal, 3, 1), (Preset::Nightmare, 2, 2)] {
+            let settings = tiny(preset, attempts, busts);
+            let mut solver = Solver::new(&settings).unwrap();
+            // fine enough that no two beliefs share a memo entry, and still within the u32 keys
+            solver.resolution = 1e9;
+            let solved = solver.solve().win_probability;
+            let expected = brute_force(&solver, &solver.uniform_belief(), attempts, busts);
+            assert!((solved - expected).abs() < 1e-9, "{:?}: {} != {}", preset, solved, expected);
+        }
+    }
+
+    #[test]
+    fn memoising_doesnt_change_the_values() {
+        let settings = tiny(Preset::Hard, 3, 2);
+        let mut shared = Solver::new(&settings).unwrap();
+        shared.resolution = 1e9;
+        let start = shared.uniform_belief();
+        let beliefs = [
+            start.clone(),
+            shared.update(&start, 1, 1, "orange", "N"),
+            shared.update(&start, 0, 2, "green", "SW"),
+            shared.rule_out(&start, 2, 0),
+            start,
+        ];
+        for belief in &beliefs {
+            for (attempts, busts) in [(3, 2), (2, 2), (3, 1)] {
+                let memoised = shared.value(belief, attempts, busts);
+                let mut fresh = Solver::new(&settings).unwrap();
+                fresh.resolution = 1e9;
+                let value = fresh.value(belief, attempts, busts);
+                assert!((memoised - value).abs() < 1e-12, "{} != {}", memoised, value);
+            }
+        }
+        assert!(!shared.memo.is_empty());
+    }
+
+    #[test]
+    fn rounding_the_memo_keys_barely_moves_the_value() {
+        let settings = tiny(Preset::Hard, 3, 2);
+        let exact = {
+            let mut solver = Solver::new(&settings).unwrap();
+            solver.resolution = 1e9;
+            solver.solve()
+        };
+        let rounded = Solver::new(&settings).unwrap().solve();
+        assert!((exact.win_probability - rounded.win_probability).abs() < 1e-3);
+        assert!(rounded.states <= exact.states);
+    }
+
+    #[test]
+    fn solver_updates_beliefs_like_the_game() {
+        // The last row of the normal preset adds up to 0.95, both divide it by its sum
+        let settings = GameSettings {
+            width: 4,
+            height: 4,
+            preset: None,
+            ..Preset::Normal.settings()
+        };
+        let last_row = settings.conditional_probabilities.last().unwrap().colors();
+        assert!((last_row.iter().sum::<f32>() - 1.0).abs() > 0.01);
+        let solver = Solver::new(&settings).unwrap();
+        for color in COLORS {
+            for direction in DIRECTIONS {
+                let mut game = Game::from_seed(settings.clone(), 3);
+                game.compute_initial_prior_probabilities();
+                game.observe(0, 0, color, direction);
+                let belief = solver.update(&solver.uniform_belief(), 0, 0, color, direction);
+                for (a, b) in game.belief.probabilities.iter().zip(&belief) {
+                    assert!((a - b).abs() < 1e-9, "{} {}: {} != {}", color, direction, a, b);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::game::Game;
+use crate::preset::Preset;
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const HISTORY_KEY: &str = "bust-ghost.history";
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub won: bool,
+    pub attempts_used: i32,
+    pub busts_used: i32,
+    pub preset: Option<Preset>,
+    pub width: i32,
+    pub height: i32,
+    pub seed: u64,
+    pub duration_ms: u64,
+    // Milliseconds since the Unix epoch
+    pub finished_at: u64,
+    // The date of the daily challenge this game was
+    #[serde(default)]
+    pub daily: Option<String>,
+}
+
+impl GameRecord {
+    pub fn new(game: &Game, won: bool, started_at: f64, finished_at: f64) -> GameRecord {
+        GameRecord {
+            won,
+            attempts_used: game.settings.score - game.score,
+            busts_used: game.settings.busts - game.busts,
+            preset: game.settings.preset,
+            width: game.columns(),
+            height: game.rows(),
+            seed: game.seed,
+            duration_ms: (finished_at - started_at).max(0.0) as u64,
+            finished_at: finished_at as u64,
+            daily: None,
+        }
+    }
+}
+
+// The file players move between browsers
+#[derive(Serialize, Deserialize)]
+struct Export {
+    version: u32,
+    games: Vec<GameRecord>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub games: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    pub current_streak: usize,
+    pub best_streak: usize,
+    pub average_attempts: f64,
+    pub average_busts: f64,
+}
+
+// `history` is expected oldest first, as stored
+pub fn summarize(history: &[GameRecord]) -> Summary {
+    if history.is_empty() {
+        return Summary::default();
+    }
+    let games = history.len();
+    let wins = history.iter().filter(|record| record.won).count();
+    let mut streak = 0;
+    let mut best_streak = 0;
+    for record in history {
+        streak = if record.won { streak + 1 } else { 0 };
+        best_streak = best_streak.max(streak);
+    }
+    Summary {
+        games,
+        wins,
+        win_rate: wins as f64 / games as f64,
+        current_streak: streak,
+        best_streak,
+        average_attempts: history.iter().map(|record| record.attempts_used as f64).sum::<f64>() / games as f64,
+        average_busts: history.iter().map(|record| record.busts_used as f64).sum::<f64>() / games as f64,
+    }
+}
+
+pub fn history() -> Vec<GameRecord> {
+    LocalStorage::get(HISTORY_KEY).unwrap_or_default()
+}
+
+fn save(history: &[GameRecord]) {
+    if let Err(err) = LocalStorage::set(HISTORY_KEY, history) {
+        gloo::console::error!(format!("Could not save the game history: {}", err));
+    }
+}
+
+pub fn record(record: GameRecord) {
+    let mut history = history();
+    history.push(record);
+    save(&history);
+}
+
+pub fn clear() {
+    LocalStorage::delete(HISTORY_KEY);
+}
+
+pub fn export_json() -> String {
+    export(history())
+}
+
+fn export(games: Vec<GameRecord>) -> String {
+    let export = Export {
+        version: EXPORT_VERSION,
+        games,
+    };
+    serde_json::to_string_pretty(&export).expect("game records always serialize")
+}
+
+// Merges the games of an exported file into the local history and returns how many were new
+pub fn import_json(json: &str) -> Result<usize, String> {
+    let mut history = history();
+    let imported = merge(&mut history, json)?;
+    save(&history);
+    Ok(imported)
+}
+
+// Adds the games of the file `history` doesn't have yet, keeping it oldest first, and returns how
+// many there were
+fn merge(history: &mut Vec<GameRecord>, json: &str) -> Result<usize, String> {
+    let export = serde_json::from_str::<Export>(json).map_err(|err| format!("Not a stats file: {}", err))?;
+    if export.version > EXPORT_VERSION {
+        return Err(format!("This stats file was made by a newer version (v{})", export.version));
+    }
+    let before = history.len();
+    for record in export.games {
+        if !history.contains(&record) {
+            history.push(record);
+        }
+    }
+    history.sort_by_key(|record| record.finished_at);
+    Ok(history.len() - before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(won: bool, attempts_used: i32, finished_at: u64) -> GameRecord {
+        GameRecord {
+            won,
+            attempts_used,
+            busts_used: if won { 1 } else { 2 },
+            preset: Some(Preset::Normal),
+            width: 12,
+            height: 9,
+            seed: finished_at,
+            duration_ms: 1000,
+            finished_at,
+            daily: None,
+        }
+    }
+
+    #[test]
+    fn an_empty_history_sums_up_to_nothing() {
+        assert_eq!(summarize(&[]), Summary::default());
+    }
+
+    #[test]
+    fn losses_end_streaks() {
+        let history = [true, true, true, false, true, false, true, true].map(|won| game(won, 4, 0));
+        let summary = summarize(&history);
+        assert_eq!((summary.games, summary.wins), (8, 6));
+        assert_eq!((summary.current_streak, summary.best_streak), (2, 3));
+        assert_eq!(summary.win_rate, 0.75);
+        assert_eq!(summarize(&history[..4]).current_streak, 0);
+    }
+
+    #[test]
+    fn averages_count_every_game() {
+        let summary = summarize(&[game(true, 2, 0), game(false, 6, 1), game(true, 7, 2)]);
+        assert_eq!(summary.average_attempts, 5.0);
+        assert!((summary.average_busts - 4.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn importing_a_file_twice_adds_its_games_once() {
+        let file = export(vec![game(true, 3, 20), game(false, 9, 5)]);
+        let mut history = vec![game(true, 4, 10), game(false, 9, 5)];
+        assert_eq!(merge(&mut history, &file), Ok(1));
+        assert_eq!(merge(&mut history, &file), Ok(0));
+        let finished = history.iter().map(|record| record.finished_at).collect::<Vec<_>>();
+        assert_eq!(finished, [5, 10, 20]);
+    }
+
+    #[test]
+    fn files_from_a_newer_version_are_refused() {
+        let file = export(vec![game(true, 3, 20)]).replace(
+            &format!("\"version\": {}", EXPORT_VERSION),
+            &format!("\"version\": {}", EXPORT_VERSION + 1),
+        );
+        let mut history = vec![];
+        assert!(merge(&mut history, &file).is_err());
+        assert!(merge(&mut history, "not a stats file").is_err());
+        assert!(history.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::i18n::{self, t, tr, use_locale, Locale};
+use crate::stats::{self, GameRecord};
+use gloo::file::callbacks::{read_as_text, FileReader};
+use gloo::file::{Blob, ObjectUrl};
+use leptos::*;
+use leptos_router::A;
+
+fn format_duration(ms: u64) -> String {
+    let seconds = ms / 1000;
+    if seconds >= 60 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+pub fn format_date(ms: u64, locale: Locale) -> String {
+    js_sys::Date::new(&(ms as f64).into())
+        .to_locale_string(locale.name(), &wasm_bindgen::JsValue::UNDEFINED)
+        .into()
+}
+
+#[component]
+pub fn StatsView() -> impl IntoView {
+    let (history, set_history) = create_signal(vec![]);
+    let (message, set_message) = create_signal(None::<String>);
+    let locale = use_locale();
+    let l10n = move |key: &'static str| move || t(locale.get(), key);
+    // The reader is cancelled when dropped, so keep it around until the file is read
+    let reader = store_value(None::<FileReader>);
+    let summary = move || history.with(|history| stats::summarize(history));
+    // local storage only exists in the browser, so the history is loaded once mounted
+    create_effect(move |_| set_history.set(stats::history()));
+
+    let handle_export = move |_| {
+        let blob = Blob::new_with_options(stats::export_json().as_str(), Some("application/json"));
+        let url = ObjectUrl::from(blob);
+        let link = html::a().attr("href", url.to_string()).attr("download", "bust-ghost-stats.json");
+        link.click();
+    };
+
+    let handle_import = move |ev: ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        let file = gloo::file::File::from(file);
+        reader.set_value(Some(read_as_text(&file, move |result| {
+            let imported = result
+                .map_err(|err| tr(locale.get_untracked(), "stats.read_failed", &[("error", err.to_string())]))
+                .and_then(|json| stats::import_json(&json));
+            match imported {
+                Ok(count) => {
+                    let count = i18n::number(locale.get_untracked(), count);
+                    set_message.set(Some(tr(locale.get_untracked(), "stats.imported", &[("count", count)])));
+                    set_history.set(stats::history());
+                }
+                Err(err) => set_message.set(Some(err)),
+            }
+        })));
+        input.set_value("");
+    };
+
+    let handle_clear = move |_| {
+        if gloo::dialogs::confirm(&t(locale.get_untracked(), "stats.confirm_clear")) {
+            stats::clear();
+            set_history.set(vec![]);
+        }
+    };
+
+    let row = move |record: &GameRecord| {
+        let locale = locale.get();
+        let preset = record.preset.map_or("preset.custom".to_string(), |preset| format!("preset.{}", preset.name()));
+        view! {
+            <tr>
+                <td>{format_date(record.finished_at, locale)}</td>
+                <td>{t(locale, &preset)}</td>
+                <td>{format!("{}x{}", i18n::number(locale, record.width), i18n::number(locale, record.height))}</td>
+                <td class=if record.won {"result--won"} else {"result--lost"}>
+                    {t(locale, if record.won {"stats.won"} else {"stats.lost"})}
+                </td>
+                <td>{i18n::number(locale, record.attempts_used)}</td>
+                <td>{i18n::number(locale, record.busts_used)}</td>
+                <td>{format_duration(record.duration_ms)}</td>
+                <td class="table__seed">{record.seed}</td>
+            </tr>
+        }
+    };
+
+    view! {
+        <div class="page">
+            <h1>{l10n("stats.title")}</h1>
+            <div class="page__summary">
+                <p>{move || tr(locale.get(), "stats.played", &[("games", i18n::number(locale.get(), summary().games))])}</p>
+                <p>{move || tr(locale.get(), "stats.win_rate", &[("rate", i18n::percent(locale.get(), summary().win_rate, 0))])}</p>
+                <p>{move || tr(locale.get(), "stats.current_streak", &[("streak", i18n::number(locale.get(), summary().current_streak))])}</p>
+                <p>{move || tr(locale.get(), "stats.best_streak", &[("streak", i18n::number(locale.get(), summary().best_streak))])}</p>
+                <p>{move || tr(locale.get(), "stats.average_attempts", &[("attempts", i18n::decimal(locale.get(), summary().average_attempts, 1))])}</p>
+                <p>{move || tr(locale.get(), "stats.average_busts", &[("busts", i18n::decimal(locale.get(), summary().average_busts, 1))])}</p>
+            </div>
+            <div class="page__row">
+                <button on:click=handle_export class="button button--inline button--success">
+                    {l10n("stats.export")}
+                </button>
+                <label class="button button--inline button--primary">
+                    {l10n("stats.import")}
+                    <input type="file" accept="application/json,.json" on:change=handle_import hidden/>
+                </label>
+                <button on:click=handle_clear class="button button--inline button--danger">
+                    {l10n("stats.clear")}
+                </button>
+            </div>
+            <p>{move || message.get().unwrap_or_default()}</p>
+            <table class="table">
+                <thead>
+                    <tr>
+                        <th>{l10n("stats.date")}</th>
+                        <th>{l10n("stats.preset")}</th>
+                        <th>{l10n("stats.board")}</th>
+                        <th>{l10n("stats.result")}</th>
+                        <th>{l10n("stats.attempts")}</th>
+                        <th>{l10n("stats.busts")}</th>
+                        <th>{l10n("stats.time")}</th>
+                        <th>{l10n("stats.seed")}</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {move || history.with(|history| history.iter().rev().map(row).collect::<Vec<_>>())}
+                </tbody>
+            </table>
+            <A href="/">{l10n("link.back")}</A>
+        </div>
+    }
+}
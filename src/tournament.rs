@@ -0,0 +1,248 @@
+// Plays every agent on the same seeded boards and rates them
+use crate::agents::{compare, Agent, GameResult};
+use crate::preset::Preset;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 16.0;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Standing {
+    pub agent: String,
+    pub games: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    // Attempts and busts used per game, a forfeit costs the whole budget
+    pub mean_cost: f64,
+    pub forfeits: usize,
+    pub rating: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Outcome {
+    pub agent: String,
+    pub won: bool,
+    pub attempts_used: i32,
+    pub busts_used: i32,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Round {
+    pub seed: u64,
+    pub outcomes: Vec<Outcome>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub preset: Preset,
+    pub seeds: Vec<u64>,
+    // Best rated first
+    pub standings: Vec<Standing>,
+    pub rounds: Vec<Round>,
+}
+
+// The score an agent rated `rating` is expected to make against one rated `opponent`, from 0 for
+// a sure loss to 1 for a sure win
+fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+// Every pair of agents plays a rated match on each seed, in the order of the seeds. The agent that
+// did better on the board wins the match, see `agents::compare`.
+fn ratings(results: &[Vec<GameResult>], agents: usize) -> Vec<f64> {
+    let mut ratings = vec![INITIAL_RATING; agents];
+    for games in results {
+        for a in 0..agents {
+            for b in a + 1..agents {
+                let score = match compare(&games[a], &games[b]) {
+                    Ordering::Greater => 1.0,
+                    Ordering::Equal => 0.5,
+                    Ordering::Less => 0.0,
+                };
+                let expected = expected_score(ratings[a], ratings[b]);
+                ratings[a] += K_FACTOR * (score - expected);
+                ratings[b] -= K_FACTOR * (score - expected);
+            }
+        }
+    }
+    ratings
+}
+
+pub fn run(agents: &mut [Box<dyn Agent>], preset: Preset, seeds: &[u64]) -> Report {
+    let settings = preset.settings();
+    let results = seeds
+        .iter()
+        .map(|seed| agents.iter_mut().map(|agent| agent.play(&settings, *seed)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let names = agents.iter().map(|agent| agent.name()).collect::<Vec<_>>();
+    let ratings = ratings(&results, agents.len());
+
+    let mut standings = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let played = results.iter().map(|games| &games[i]).collect::<Vec<_>>();
+            let games = played.len();
+            let wins = played.iter().filter(|result| result.won).count();
+            let cost = played
+                .iter()
+                .map(|result| match result.error {
+                    Some(_) => settings.score + settings.busts,
+                    None => result.attempts_used + result.busts_used,
+                })
+                .sum::<i32>();
+            Standing {
+                agent: name.clone(),
+                games,
+                wins,
+                win_rate: wins as f64 / games.max(1) as f64,
+                mean_cost: cost as f64 / games.max(1) as f64,
+                forfeits: played.iter().filter(|result| result.error.is_some()).count(),
+                rating: ratings[i],
+            }
+        })
+        .collect::<Vec<_>>();
+    standings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+
+    let rounds = results
+        .iter()
+        .zip(seeds)
+        .map(|(games, seed)| Round {
+            seed: *seed,
+            outcomes: games
+                .iter()
+                .zip(&names)
+                .map(|(result, name)| Outcome {
+                    agent: name.clone(),
+                    won: result.won,
+                    attempts_used: result.attempts_used,
+                    busts_used: result.busts_used,
+                    error: result.error.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    Report {
+        preset,
+        seeds: seeds.to_vec(),
+        standings,
+        rounds,
+    }
+}
+
+impl Report {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports always serialize")
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "# Tournament on {}\n\n{} boards, seeds {} to {}\n\n",
+            self.preset.label(),
+            self.seeds.len(),
+            self.seeds.first().copied().unwrap_or_default(),
+            self.seeds.last().copied().unwrap_or_default()
+        );
+        markdown.push_str("| Rank | Agent | Rating | Win rate | Mean cost | Forfeits |\n");
+        markdown.push_str("| ---: | --- | ---: | ---: | ---: | ---: |\n");
+        for (i, standing) in self.standings.iter().enumerate() {
+            markdown.push_str(&format!(
+                "| {} | `{}` | {:.0} | {:.1}% | {:.2} | {} |\n",
+                i + 1,
+                standing.agent,
+                standing.rating,
+                standing.win_rate * 100.0,
+                standing.mean_cost,
+                standing.forfeits
+            ));
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(won: bool, attempts_used: i32) -> GameResult {
+        GameResult {
+            seed: 0,
+            won,
+            attempts_used,
+            busts_used: 1,
+            actions: vec![],
+            error: None,
+        }
+    }
+
+    fn forfeit() -> GameResult {
+        GameResult {
+            error: Some("The bot exited".to_string()),
+            ..result(false, 0)
+        }
+    }
+
+    // Three agents over a few seeds, with wins, losses, draws and a forfeit
+    fn results() -> Vec<Vec<GameResult>> {
+        vec![
+            vec![result(true, 3), result(true, 5), result(false, 30)],
+            vec![result(true, 4), result(true, 4), forfeit()],
+            vec![result(false, 30), result(true, 2), result(true, 9)],
+            vec![result(true, 3), result(false, 30), result(true, 1)],
+        ]
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn expected_score_follows_the_rating_difference() {
+        assert_close(expected_score(1500.0, 1500.0), 0.5);
+        // 400 points ahead wins ten times as often as it loses
+        assert_close(expected_score(1900.0, 1500.0), 10.0 / 11.0);
+        assert_close(expected_score(1500.0, 1900.0), 1.0 / 11.0);
+        assert!(expected_score(1600.0, 1500.0) > expected_score(1550.0, 1500.0));
+    }
+
+    #[test]
+    fn expected_scores_of_both_sides_add_up_to_1() {
+        for (a, b) in [(1500.0, 1500.0), (1720.0, 1310.0), (900.0, 2400.0)] {
+            assert_close(expected_score(a, b) + expected_score(b, a), 1.0);
+        }
+    }
+
+    #[test]
+    fn a_win_between_equals_moves_half_the_k_factor() {
+        let ratings = ratings(&[vec![result(true, 3), result(true, 4)]], 2);
+        assert_close(ratings[0], INITIAL_RATING + K_FACTOR / 2.0);
+        assert_close(ratings[1], INITIAL_RATING - K_FACTOR / 2.0);
+    }
+
+    #[test]
+    fn a_draw_between_equals_changes_nothing() {
+        let ratings = ratings(&[vec![result(true, 3), result(true, 3)]], 2);
+        assert_eq!(ratings, vec![INITIAL_RATING; 2]);
+    }
+
+    #[test]
+    fn ratings_are_zero_sum() {
+        let ratings = ratings(&results(), 3);
+        assert_close(ratings.iter().sum::<f64>(), 3.0 * INITIAL_RATING);
+    }
+
+    #[test]
+    fn ratings_are_symmetric() {
+        let results = results().into_iter().map(|games| games[..2].to_vec()).collect::<Vec<_>>();
+        let swapped = results
+            .iter()
+            .map(|games| vec![games[1].clone(), games[0].clone()])
+            .collect::<Vec<_>>();
+        let (rated, swapped) = (ratings(&results, 2), ratings(&swapped, 2));
+        assert_close(rated[0], swapped[1]);
+        assert_close(rated[1], swapped[0]);
+    }
+}
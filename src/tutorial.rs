@@ -0,0 +1,193 @@
+// The tutorial: a small board with a fixed ghost and readings written out instead of drawn, so that
+// the explanations always walk through the same numbers. Every reading is explained in the steps
+// of the posterior update, with the factors `Game::explain_reading` gives.
+use crate::game::{BeliefUpdate, Direction, Game, GameSettings, SensorColor};
+use crate::preset::Preset;
+
+// Places the ghost on D2
+pub const SEED: u64 = 7;
+
+pub struct ScriptedReading {
+    pub x: i32,
+    pub y: i32,
+    pub color: SensorColor,
+    pub direction: Direction,
+    // The cell the explanations follow until the player picks another
+    pub focus: (i32, i32),
+}
+
+// Readings the ghost on D2 could well give. The second follows a cell the direction rules out.
+pub const READINGS: [ScriptedReading; 3] = [
+    ScriptedReading {
+        x: 3,
+        y: 1,
+        color: SensorColor::Yellow,
+        direction: Direction::NE,
+        focus: (1, 3),
+    },
+    ScriptedReading {
+        x: 1,
+        y: 1,
+        color: SensorColor::Orange,
+        direction: Direction::E,
+        focus: (0, 2),
+    },
+    ScriptedReading {
+        x: 2,
+        y: 3,
+        color: SensorColor::Orange,
+        direction: Direction::N,
+        focus: (1, 3),
+    },
+];
+
+// The normal preset on a board small enough to read every probability
+pub fn settings() -> GameSettings {
+    GameSettings {
+        width: 5,
+        height: 5,
+        preset: None,
+        ..Preset::Normal.settings()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    // Every cell is as likely as the others
+    Prior,
+    // What the sensor read
+    Reading,
+    // The color's probability in the sensor row for the cell's distance
+    Likelihood,
+    Direction,
+    // Dividing by the sum so that the probabilities add up to 1 again
+    Normalisation,
+    // Where the ghost was
+    Ghost,
+}
+
+// `reading` is the index in `READINGS` of the reading a stage explains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub stage: Stage,
+    pub reading: usize,
+}
+
+pub fn steps() -> Vec<Step> {
+    let mut steps = vec![Step { stage: Stage::Prior, reading: 0 }];
+    for reading in 0..READINGS.len() {
+        for stage in [Stage::Reading, Stage::Likelihood, Stage::Direction, Stage::Normalisation] {
+            steps.push(Step { stage, reading });
+        }
+    }
+    steps.push(Step {
+        stage: Stage::Ghost,
+        reading: READINGS.len() - 1,
+    });
+    steps
+}
+
+pub struct Tutorial {
+    // After every reading
+    pub game: Game,
+    // In the order of `READINGS`
+    pub updates: Vec<BeliefUpdate>,
+}
+
+impl Tutorial {
+    pub fn new() -> Tutorial {
+        let mut game = Game::from_seed(settings(), SEED);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        let mut updates = vec![];
+        for reading in &READINGS {
+            let (color, direction) = (reading.color.name(), reading.direction.name());
+            updates.push(game.explain_reading(reading.x, reading.y, color, direction));
+            game.observe(reading.x, reading.y, color, direction);
+        }
+        Tutorial { game, updates }
+    }
+
+    // Whether the reading of `step` has been normalised into the probabilities yet
+    fn normalised(step: Step) -> bool {
+        matches!(step.stage, Stage::Normalisation | Stage::Ghost)
+    }
+
+    // The probability of the cell (x, y) the board shows at `step`
+    pub fn probability(&self, step: Step, x: i32, y: i32) -> f64 {
+        let factors = &self.updates[step.reading].cells[self.game.index(x, y)];
+        match Tutorial::normalised(step) {
+            true => factors.posterior,
+            false => factors.prior,
+        }
+    }
+
+    // The color the board shows on the cell (x, y) at `step`, once the cell is sensed
+    pub fn color(&self, step: Step, x: i32, y: i32) -> Option<SensorColor> {
+        let sensed = match step.stage {
+            Stage::Prior => 0,
+            _ => step.reading + 1,
+        };
+        READINGS[..sensed]
+            .iter()
+            .find(|reading| (reading.x, reading.y) == (x, y))
+            .map(|reading| reading.color)
+    }
+
+    // The most likely cell once every reading is in
+    pub fn best(&self) -> (i32, i32) {
+        let cell = self
+            .game
+            .cells
+            .iter()
+            .zip(&self.game.belief.probabilities)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(cell, _)| cell)
+            .expect("the board has cells");
+        (cell.x, cell.y)
+    }
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Tutorial::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_seed_puts_the_ghost_on_d2() {
+        assert_eq!(Tutorial::new().game.ghost_position, (1, 3));
+    }
+
+    #[test]
+    fn the_readings_point_at_the_ghost() {
+        let (x, y) = Tutorial::new().game.ghost_position;
+        for reading in &READINGS {
+            assert_eq!(Direction::between(reading.x, reading.y, x, y), reading.direction);
+        }
+    }
+
+    #[test]
+    fn every_update_ends_on_the_posterior_the_game_computes() {
+        let tutorial = Tutorial::new();
+        let mut game = Game::from_seed(settings(), SEED);
+        game.place_ghost();
+        game.compute_initial_prior_probabilities();
+        for (reading, update) in READINGS.iter().zip(&tutorial.updates) {
+            game.observe(reading.x, reading.y, reading.color.name(), reading.direction.name());
+            let posteriors = update.cells.iter().map(|factors| factors.posterior).collect::<Vec<_>>();
+            assert_eq!(posteriors, game.belief.probabilities);
+        }
+        assert_eq!(game.belief.probabilities, tutorial.game.belief.probabilities);
+    }
+
+    #[test]
+    fn the_most_likely_cell_is_the_ghost() {
+        let tutorial = Tutorial::new();
+        assert_eq!(tutorial.best(), tutorial.game.ghost_position);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::board::{cell_label, column_label};
+use crate::game::{Direction, SensorColor, DIRECTIONS};
+use crate::i18n::{self, t, tr, use_locale, Locale};
+use crate::index::{cell_class, describe_cell};
+use crate::palette::{glyph, Appearance};
+use crate::tutorial::{self, Stage, Step, Tutorial, READINGS};
+use leptos::*;
+use leptos_router::A;
+
+fn direction_words(locale: Locale, direction: Direction) -> String {
+    t(locale, &format!("direction.words.{}", direction.name()))
+}
+
+// What `step` says about the cell `focus`, with the numbers of the tutorial's game
+fn explanation(locale: Locale, tutorial: &Tutorial, step: Step, focus: (i32, i32)) -> String {
+    let game = &tutorial.game;
+    let reading = &READINGS[step.reading];
+    let update = &tutorial.updates[step.reading];
+    let factors = update.cells[game.index(focus.0, focus.1)];
+    let percent = |probability: f64| i18n::percent(locale, probability, 2);
+    let factor = |factor: f64| i18n::decimal(locale, factor, 4);
+    let cell = ("cell", cell_label(reading.x, reading.y));
+    let focused = ("focus", cell_label(focus.0, focus.1));
+    match step.stage {
+        Stage::Prior => tr(
+            locale,
+            "tutorial.prior",
+            &[("cells", i18n::number(locale, game.cells.len())), ("prior", percent(factors.prior))],
+        ),
+        Stage::Reading => tr(
+            locale,
+            "tutorial.reading",
+            &[
+                cell,
+                ("color", i18n::color(locale, Some(reading.color))),
+                ("direction", direction_words(locale, reading.direction)),
+                focused,
+            ],
+        ),
+        Stage::Likelihood => {
+            let distance = (focus.0 - reading.x).abs() + (focus.1 - reading.y).abs();
+            let row = game.conditional_probabilities[factors.row].distance();
+            let mut text = tr(
+                locale,
+                "tutorial.likelihood",
+                &[
+                    focused,
+                    ("distance", i18n::number(locale, distance)),
+                    cell,
+                    ("row", i18n::number(locale, row)),
+                    ("color", i18n::color(locale, Some(reading.color))),
+                    ("factor", factor(factors.color)),
+                ],
+            );
+            if distance > row {
+                text += " ";
+                text += &tr(locale, "tutorial.last_row", &[("row", i18n::number(locale, row))]);
+            }
+            text
+        }
+        Stage::Direction => {
+            let side = Direction::between(reading.x, reading.y, focus.0, focus.1);
+            let side_text = match side {
+                Direction::Bingo => t(locale, "tutorial.side_here"),
+                side => tr(locale, "tutorial.side", &[("direction", direction_words(locale, side))]),
+            };
+            let reliability = game.settings.direction_reliability as f64;
+            if side == reading.direction {
+                tr(
+                    locale,
+                    "tutorial.direction_match",
+                    &[
+                        cell,
+                        focused,
+                        ("side", side_text),
+                        ("reliability", percent(reliability)),
+                        ("factor", factor(factors.direction)),
+                    ],
+                )
+            } else {
+                tr(
+                    locale,
+                    "tutorial.direction_miss",
+                    &[
+                        cell,
+                        focused,
+                        ("side", side_text),
+                        ("direction", direction_words(locale, reading.direction)),
+                        ("unreliability", percent(1.0 - reliability)),
+                        ("others", i18n::number(locale, DIRECTIONS.len() - 1)),
+                        ("factor", factor(factors.direction)),
+                    ],
+                )
+            }
+        }
+        Stage::Normalisation => tr(
+            locale,
+            "tutorial.normalisation",
+            &[
+                focused,
+                ("prior", percent(factors.prior)),
+                ("unnormalised", i18n::decimal(locale, factors.unnormalised(), 6)),
+                ("sum", i18n::decimal(locale, update.normalisation, 6)),
+                ("posterior", percent(factors.posterior)),
+            ],
+        ),
+        Stage::Ghost => {
+            let (x, y) = game.ghost_position;
+            let best = tutorial.best();
+            tr(
+                locale,
+                "tutorial.ghost",
+                &[
+                    ("ghost", cell_label(x, y)),
+                    ("prior", percent(tutorial.updates[0].cells[game.index(x, y)].prior)),
+                    ("probability", percent(game.probability(x, y))),
+                    ("best", cell_label(best.0, best.1)),
+                    ("best_probability", percent(game.probability(best.0, best.1))),
+                ],
+            )
+        }
+    }
+}
+
+// Walks through the readings of `tutorial::READINGS` one step of the update at a time
+#[component]
+pub fn TutorialView() -> impl IntoView {
+    let tutorial = store_value(Tutorial::new());
+    let steps = store_value(tutorial::steps());
+    let count = steps.with_value(Vec::len);
+    let (index, set_index) = create_signal(0);
+    let step = move || steps.with_value(|steps| steps[index.get()]);
+    // The cell the explanations follow, the scripted one until the player clicks another
+    let (picked, set_picked) = create_signal(None::<(i32, i32)>);
+    let focus = move || picked.get().unwrap_or(READINGS[step().reading].focus);
+    let locale = use_locale();
+    let l10n = move |key: &'static str| move || t(locale.get(), key);
+    let appearance = use_context::<RwSignal<Appearance>>().unwrap_or_else(|| create_rw_signal(Appearance::default()));
+
+    let go = move |to: usize| {
+        // a new reading goes back to the cell the script follows
+        if steps.with_value(|steps| steps[to].reading != steps[index.get_untracked()].reading) {
+            set_picked.set(None);
+        }
+        set_index.set(to);
+    };
+
+    let board = move || {
+        let (step, focus, locale) = (step(), focus(), locale.get());
+        let glyphs = appearance.with(|appearance| appearance.glyphs);
+        tutorial.with_value(|tutorial| {
+            let game = &tutorial.game;
+            let reading = &READINGS[step.reading];
+            let sensed = (step.stage != Stage::Prior).then_some((reading.x, reading.y));
+            let header = view! {
+                <div role="row" aria-rowindex=1 class="board__row">
+                    <div></div>
+                    {(0..game.columns()).map(|y| view! {
+                        <div role="columnheader" aria-colindex=y + 2 class="board__column-header">{column_label(y)}</div>
+                    }).collect::<Vec<_>>()}
+                </div>
+            };
+            let rows = (0..game.rows()).map(|x| {
+                let cells = (0..game.columns()).map(|y| {
+                    let color = tutorial.color(step, x, y);
+                    let probability = tutorial.probability(step, x, y);
+                    let mut class = cell_class(color, (x, y) == focus);
+                    if Some((x, y)) == sensed {
+                        class += " tutorial__sensed";
+                    }
+                    let ghost = step.stage == Stage::Ghost && (x, y) == game.ghost_position;
+                    view! {
+                        <button
+                            role="gridcell"
+                            aria-colindex=y + 2
+                            aria-label=describe_cell(locale, x, y, color, Some(probability))
+                            aria-selected=((x, y) == focus).to_string()
+                            class=class
+                            on:click=move |_| set_picked.set(Some((x, y)))
+                        >
+                            {ghost.then_some("👻")}
+                            {(glyphs && color.is_some()).then(|| glyph(color).to_string())}
+                            {i18n::percent(locale, probability, 1)}
+                        </button>
+                    }
+                }).collect::<Vec<_>>();
+                view! {
+                    <div role="row" aria-rowindex=x + 2 class="board__row">
+                        <div role="rowheader" aria-colindex=1 class="board__row-header">{x + 1}</div>
+                        {cells}
+                    </div>
+                }
+            }).collect::<Vec<_>>();
+            view! {
+                <div
+                    role="grid"
+                    aria-label=t(locale, "board.label")
+                    aria-rowcount=game.rows() + 1
+                    aria-colcount=game.columns() + 1
+                    class="board tutorial__board"
+                    dir="ltr"
+                    style=format!(
+                        "grid-template-columns: auto repeat({}, 1fr); grid-template-rows: auto repeat({}, 1fr);",
+                        game.columns(),
+                        game.rows()
+                    )
+                >
+                    {header}
+                    {rows}
+                </div>
+            }
+        })
+    };
+
+    // The rows of `ConditionalProbabilities` as the sensor draws from them, with the one the
+    // likelihood is read from marked
+    let sensor_table = move || {
+        let (step, focus, locale) = (step(), focus(), locale.get());
+        (step.stage == Stage::Likelihood).then(|| tutorial.with_value(|tutorial| {
+            let game = &tutorial.game;
+            let reading = &READINGS[step.reading];
+            let used = tutorial.updates[step.reading].cells[game.index(focus.0, focus.1)].row;
+            view! {
+                <table class="table tutorial__sensor">
+                    <thead>
+                        <tr>
+                            <th>{t(locale, "tutorial.distance")}</th>
+                            {SensorColor::ALL.into_iter().map(|color| view! {
+                                <th>{i18n::color(locale, Some(color))}</th>
+                            }).collect::<Vec<_>>()}
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {game.conditional_probabilities.iter().enumerate().map(|(i, row)| view! {
+                            <tr class=(i == used).then_some("tutorial__used")>
+                                <td>{i18n::number(locale, row.distance())}</td>
+                                {SensorColor::ALL.into_iter().map(|color| view! {
+                                    <td class=(i == used && color == reading.color).then_some("tutorial__factor")>
+                                        {i18n::decimal(locale, row.likelihood(color), 2)}
+                                    </td>
+                                }).collect::<Vec<_>>()}
+                            </tr>
+                        }).collect::<Vec<_>>()}
+                    </tbody>
+                </table>
+            }
+        }))
+    };
+
+    // The numbers of the focused cell so far in the update
+    let numbers = move || {
+        let (step, focus, locale) = (step(), focus(), locale.get());
+        let shown = match step.stage {
+            Stage::Likelihood => 2,
+            Stage::Direction => 3,
+            Stage::Normalisation => 6,
+            _ => 0,
+        };
+        (shown > 0).then(|| tutorial.with_value(|tutorial| {
+            let update = &tutorial.updates[step.reading];
+            let factors = update.cells[tutorial.game.index(focus.0, focus.1)];
+            let rows = [
+                ("tutorial.numbers.prior", i18n::percent(locale, factors.prior, 2)),
+                ("tutorial.numbers.color", i18n::decimal(locale, factors.color, 4)),
+                ("tutorial.numbers.direction", i18n::decimal(locale, factors.direction, 4)),
+                ("tutorial.numbers.unnormalised", i18n::decimal(locale, factors.unnormalised(), 6)),
+                ("tutorial.numbers.sum", i18n::decimal(locale, update.normalisation, 6)),
+                ("tutorial.numbers.posterior", i18n::percent(locale, factors.posterior, 2)),
+            ];
+            view! {
+                <table class="table tutorial__numbers">
+                    <caption>{cell_label(focus.0, focus.1)}</caption>
+                    <tbody>
+                        {rows.into_iter().take(shown).map(|(key, value)| view! {
+                            <tr>
+                                <th scope="row">{t(locale, key)}</th>
+                                <td>{value}</td>
+                            </tr>
+                        }).collect::<Vec<_>>()}
+                    </tbody>
+                </table>
+            }
+        }))
+    };
+
+    view! {
+        <div class="page tutorial" style=move || appearance.with(|appearance| appearance.palette.css_variables())>
+            <h1>{l10n("tutorial.title")}</h1>
+            <p>
+                {move || tr(
+                    locale.get(),
+                    "tutorial.step",
+                    &[("step", i18n::number(locale.get(), index.get() + 1)), ("steps", i18n::number(locale.get(), count))],
+                )}
+            </p>
+            <div class="tutorial__body">
+                {board}
+                <div class="tutorial__explanation">
+                    <p aria-live="polite">
+                        {move || tutorial.with_value(|tutorial| explanation(locale.get(), tutorial, step(), focus()))}
+                    </p>
+                    {sensor_table}
+                    {numbers}
+                </div>
+            </div>
+            <div class="page__row">
+                <button
+                    on:click=move |_| go(index.get_untracked() - 1)
+                    prop:disabled=move || index.get() == 0
+                    class="button button--inline"
+                >
+                    {l10n("button.previous")}
+                </button>
+                {move || if index.get() + 1 < count {
+                    view! {
+                        <button on:click=move |_| go(index.get_untracked() + 1) class="button button--inline button--primary">
+                            {l10n("button.next")}
+                        </button>
+                    }.into_view()
+                } else {
+                    view! { <A href="/" class="button button--inline button--success">{l10n("tutorial.play")}</A> }.into_view()
+                }}
+            </div>
+            <A href="/">{l10n("link.back")}</A>
+        </div>
+    }
+}
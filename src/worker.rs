@@ -0,0 +1,240 @@
+// The engine in a Web Worker, so that the posterior updates and the hints of large boards don't
+// freeze the page. The worker plays the game and `GameView` mirrors it from the messages sent back:
+// first what an action revealed, then the posterior once it is ready, summed over blocks of cells
+// so that the message stays small whatever the size of the board.
+use crate::agents;
+use crate::game::{Action, Event, Game, GameSettings, Status};
+use crate::inference::{Heatmap, InferenceEngine};
+use gloo::worker::{Codec, HandlerId, Worker, WorkerScope};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+// Where Trunk puts the worker built from `src/bin/engine-worker.rs`, relative to the page
+pub const WORKER_PATH: &str = "engine-worker.js";
+// Boards with more cells than this are played through the worker, when the app has one
+pub const WORKER_ABOVE: i32 = 40_000;
+// The most blocks `EngineOutput::Posterior` cuts the board into, about as many as the canvas has
+// pixels for the board zoomed out
+const POSTERIOR_BLOCKS: i32 = 40_000;
+
+// Messages go through JSON, bincode can't read the tagged `Action` and `Event`
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<I: Serialize>(input: I) -> JsValue {
+        JsValue::from_str(&serde_json::to_string(&input).expect("engine messages always serialize"))
+    }
+
+    fn decode<O: for<'de> Deserialize<'de>>(input: JsValue) -> O {
+        parse(&input.as_string().unwrap_or_default())
+    }
+}
+
+// A message that doesn't parse becomes the `Undecodable` variant both message types have, which
+// the worker answers and the page reports instead of either side panicking
+fn parse<O: DeserializeOwned>(text: &str) -> O {
+    serde_json::from_str(text).unwrap_or_else(|err| {
+        serde_json::from_value(serde_json::json!({ "Undecodable": err.to_string() }))
+            .expect("engine messages have an Undecodable variant")
+    })
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EngineInput {
+    // Starts a game, every output about it carries `game`. Online games have no ghost to place.
+    Start {
+        game: u32,
+        settings: GameSettings,
+        seed: u64,
+        place_ghost: bool,
+    },
+    // Plays an action with the rules of the game
+    Play(Action),
+    // What an action played elsewhere revealed, by the server in online games
+    Observe(Event),
+    // What `agents::hint` would play
+    Hint,
+    // A message the worker couldn't read
+    Undecodable(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EngineOutput {
+    // What an action revealed, sent before the posterior is updated
+    Played {
+        game: u32,
+        action: Action,
+        event: Event,
+        score: i32,
+        busts: i32,
+        status: Status,
+    },
+    Rejected {
+        game: u32,
+        action: Action,
+        error: String,
+    },
+    // The posterior after the `version`-th update of the game, by block of cells
+    Posterior {
+        game: u32,
+        version: u32,
+        heatmap: Heatmap,
+    },
+    Hint {
+        game: u32,
+        action: Action,
+    },
+    // A message one side couldn't read, with why
+    Undecodable(String),
+}
+
+pub struct EngineWorker {
+    game: Option<(u32, Game)>,
+    version: u32,
+}
+
+impl EngineWorker {
+    // Updates the posterior with a new reading or a missed bust and sends it back
+    fn update_posterior(&mut self, scope: &WorkerScope<Self>, to: HandlerId, event: Event) {
+        let Some((id, game)) = &mut self.game else {
+            return;
+        };
+        match event {
+            Event::Reading { x, y, color, direction } => {
+                game.update_posterior_ghost_location_probabilities(color, x, y, direction)
+            }
+            Event::Bust { x, y, hit: false } => game.rule_out(x, y),
+            Event::Bust { .. } => return,
+        }
+        self.version += 1;
+        let heatmap = game.belief.heatmap(Heatmap::bin_for(&game.settings, POSTERIOR_BLOCKS));
+        scope.respond(
+            to,
+            EngineOutput::Posterior {
+                game: *id,
+                version: self.version,
+                heatmap,
+            },
+        );
+    }
+}
+
+impl Worker for EngineWorker {
+    type Message = ();
+    type Input = EngineInput;
+    type Output = EngineOutput;
+
+    fn create(_scope: &WorkerScope<Self>) -> Self {
+        EngineWorker { game: None, version: 0 }
+    }
+
+    fn update(&mut self, _scope: &WorkerScope<Self>, _message: ()) {}
+
+    fn received(&mut self, scope: &WorkerScope<Self>, input: EngineInput, from: HandlerId) {
+        match input {
+            EngineInput::Start {
+                game: id,
+                settings,
+                seed,
+                place_ghost,
+            } => {
+                let mut game = Game::from_seed(settings, seed);
+                // the posterior is updated after the reading is sent back
+                game.update_belief = false;
+                if place_ghost {
+                    game.place_ghost();
+                }
+                game.compute_initial_prior_probabilities();
+                self.game = Some((id, game));
+                self.version = 0;
+            }
+            EngineInput::Play(action) => {
+                let Some((id, game)) = &mut self.game else {
+                    return;
+                };
+                let (x, y) = action.cell();
+                let first_reading = game.contains(x, y) && !game.cell(x, y).visited();
+                let output = match game.apply(action) {
+                    Ok(event) => EngineOutput::Played {
+                        game: *id,
+                        action,
+                        event,
+                        score: game.score,
+                        busts: game.busts,
+                        status: game.status,
+                    },
+                    Err(error) => EngineOutput::Rejected { game: *id, action, error },
+                };
+                let learned = match &output {
+                    EngineOutput::Played {
+                        event: Event::Reading { .. },
+                        ..
+                    } if !first_reading => None,
+                    EngineOutput::Played { event, .. } => Some(event.clone()),
+                    _ => None,
+                };
+                scope.respond(from, output);
+                if let Some(event) = learned {
+                    self.update_posterior(scope, from, event);
+                }
+            }
+            EngineInput::Observe(event) => {
+                let Some((_, game)) = &mut self.game else {
+                    return;
+                };
+                let (x, y) = event.cell();
+                if !game.contains(x, y) {
+                    return;
+                }
+                if let Event::Reading { color, direction, .. } = &event {
+                    if game.cell(x, y).visited() {
+                        return;
+                    }
+                    game.observe(x, y, color, direction);
+                }
+                self.update_posterior(scope, from, event);
+            }
+            EngineInput::Hint => {
+                let Some((id, game)) = &self.game else {
+                    return;
+                };
+                let action = agents::hint(game);
+                scope.respond(from, EngineOutput::Hint { game: *id, action });
+            }
+            EngineInput::Undecodable(error) => scope.respond(from, EngineOutput::Undecodable(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip_through_json() {
+        let event = Event::Bust { x: 1, y: 2, hit: false };
+        let parsed: EngineInput = parse(&serde_json::to_string(&EngineInput::Observe(event.clone())).unwrap());
+        assert!(matches!(parsed, EngineInput::Observe(parsed) if parsed == event));
+    }
+
+    #[test]
+    fn unreadable_messages_become_undecodable() {
+        assert!(matches!(parse::<EngineInput>("{\"Play\": 3}"), EngineInput::Undecodable(_)));
+        assert!(matches!(parse::<EngineOutput>("not json"), EngineOutput::Undecodable(_)));
+    }
+
+    #[test]
+    fn the_posterior_of_a_large_board_is_a_small_message() {
+        use crate::inference::ExactGrid;
+        let settings = GameSettings { width: 1000, height: 1000, ..GameSettings::default() };
+        let mut belief = ExactGrid::new(&settings);
+        belief.observe(500, 500, "red", "N");
+        let heatmap = belief.heatmap(Heatmap::bin_for(&settings, POSTERIOR_BLOCKS));
+        let output = EngineOutput::Posterior { game: 1, version: 1, heatmap: heatmap.clone() };
+        let text = serde_json::to_string(&output).unwrap();
+        assert!(text.len() < 1_000_000, "{} bytes", text.len());
+        let parsed = parse::<EngineOutput>(&text);
+        assert!(matches!(parsed, EngineOutput::Posterior { heatmap: parsed, .. } if parsed == heatmap));
+    }
+}